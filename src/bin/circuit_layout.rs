@@ -1,118 +1,141 @@
-use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::thread;
+use std::time::Instant;
 
 use classic_computer_science_problems::csp::*;
+use classic_computer_science_problems::packing::{Packing, Rect};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct CircuitLocation {
-    row: usize,
-    column: usize,
-    rotated: bool,
-}
+/// (高さ, 幅)
+type Size = (usize, usize);
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct Circuit(usize, usize);
+/// 盤面と、回さずに置く回路の組。どちらも面積は足りるが配置できない。
+/// 5x7 では高さ 5 の回路がすべての行をふさぐので、幅 7 の回路は置けない。
+/// 行き詰まりの原因がその前に置いた回路ではないことが多く、backjumping で節点が減る。
+const OVER_CONSTRAINED: [(Size, &[Size]); 2] = [
+    ((5, 7), &[(2, 6), (2, 4), (1, 7), (5, 1), (1, 2)]),
+    (
+        (12, 10),
+        &[(12, 1), (11, 1), (12, 5), (8, 2), (6, 1), (1, 5)],
+    ),
+];
 
-fn generate_domain(circuit: &Circuit, rows: usize, columns: usize) -> Vec<CircuitLocation> {
-    let mut domain = Vec::new();
-    for row in 0..rows {
-        for column in 0..columns {
-            if row + circuit.0 <= rows && column + circuit.1 <= columns {
-                domain.push(CircuitLocation {
-                    row,
-                    column,
-                    rotated: false,
-                });
-            }
-            // 90 degree rotation
-            if circuit.0 != circuit.1 {
-                if row + circuit.1 <= rows && column + circuit.0 <= columns {
-                    domain.push(CircuitLocation {
-                        row,
-                        column,
-                        rotated: true,
-                    });
-                }
-            }
-        }
-    }
-    domain
+fn circuits(sizes: &[Size]) -> Vec<Rect> {
+    sizes
+        .iter()
+        .map(|(height, width)| Rect::new(*height, *width))
+        .collect()
 }
 
-struct CircuitConstraint {
-    circuit: Vec<Circuit>,
+fn over_constrained() -> Vec<Packing> {
+    OVER_CONSTRAINED
+        .iter()
+        .map(|((rows, columns), sizes)| {
+            let mut packing = Packing::new(*rows, *columns, circuits(sizes));
+            packing.allow_rotation = false;
+            packing
+        })
+        .collect()
 }
 
-impl Constraint<Circuit, CircuitLocation> for CircuitConstraint {
-    fn variables(&self) -> Vec<Circuit> {
-        self.circuit.clone()
+/// 探索の方法ごとに調べた節点の数を比べる。
+fn compare(packing: &Packing) -> Result<(), CspError<usize>> {
+    println!(
+        "{}x{} board, {} circuits",
+        packing.rows,
+        packing.columns,
+        packing.items.len()
+    );
+    let csp = packing.csp()?;
+    for strategy in [
+        SearchStrategy::Chronological,
+        SearchStrategy::Backjumping,
+        SearchStrategy::BackjumpingWithNogoods,
+    ] {
+        let start = Instant::now();
+        let (solution, stats) = csp.search(strategy);
+        println!(
+            "  {:?}: {} (nodes: {}, nogoods: {}, {:?})",
+            strategy,
+            if solution.is_some() {
+                "solved"
+            } else {
+                "no solution"
+            },
+            stats.nodes,
+            stats.nogoods,
+            start.elapsed()
+        );
     }
+    let threads = thread::available_parallelism().map_or(4, |n| n.get());
+    let start = Instant::now();
+    let solution = csp.parallel_search(threads, 0);
+    println!(
+        "  Parallel ({} threads): {} ({:?})",
+        threads,
+        if solution.is_some() {
+            "solved"
+        } else {
+            "no solution"
+        },
+        start.elapsed()
+    );
+    Ok(())
+}
 
-    fn satisfied(&self, assignment: &HashMap<Circuit, CircuitLocation>) -> bool {
-        let mut used = vec![vec![false; COLUMNS + 1]; ROWS + 1];
-        for (circuit, location) in assignment {
-            let (height, width) = if location.rotated {
-                (circuit.1, circuit.0)
-            } else {
-                (circuit.0, circuit.1)
-            };
-            for row in location.row..location.row + height {
-                for column in location.column..location.column + width {
-                    if used[row][column] {
-                        return false;
-                    }
-                    used[row][column] = true;
-                }
+/// 9x9 の盤面に回路を並べて表示し、探索の方法を比べる。
+/// `--svg FILE` を付けると配置を SVG でも書き出す。
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().collect::<Vec<_>>();
+    let svg = args
+        .iter()
+        .position(|arg| arg == "--svg")
+        .and_then(|i| args.get(i + 1));
+
+    let small = circuits(&[(4, 4), (3, 3), (1, 6), (2, 2), (2, 5)]);
+    let packing = Packing::new(9, 9, small.clone());
+    match packing.solve()? {
+        Some(layout) => {
+            print!("{}", layout);
+            if let Some(path) = svg {
+                fs::write(path, layout.to_svg(20))?;
             }
         }
-        true
+        None => println!("No solution found!"),
     }
-}
 
-const ROWS: usize = 9;
-const COLUMNS: usize = 9;
+    println!();
+    match Packing::strip_pack(6, small, true)? {
+        Some(layout) => print!("Width 6 needs height {}:\n{}", layout.rows, layout),
+        None => println!("No solution found!"),
+    }
 
-fn main() {
-    let circuits = vec![
-        Circuit(4, 4),
-        Circuit(3, 3),
-        Circuit(1, 6),
-        Circuit(2, 2),
-        Circuit(2, 5),
-    ];
-    let mut domains = HashMap::new();
-    for circuit in &circuits {
-        domains.insert(circuit.clone(), generate_domain(circuit, ROWS, COLUMNS));
+    println!();
+    compare(&packing)?;
+    for packing in over_constrained() {
+        compare(&packing)?;
     }
-    let mut csp = CSP::new(circuits.clone(), domains);
-    csp.add_constraint(CircuitConstraint {
-        circuit: circuits.clone(),
-    });
-    let solution = csp.backtracking_search();
-    if let Some(solution) = solution {
-        let mut grid = vec![vec![0; COLUMNS + 1]; ROWS + 1];
-        for (circuit, location) in solution {
-            let (height, width) = if location.rotated {
-                (circuit.1, circuit.0)
-            } else {
-                (circuit.0, circuit.1)
-            };
-            for row in location.row..location.row + height {
-                for column in location.column..location.column + width {
-                    grid[row][column] = circuits.iter().position(|c| c == &circuit).unwrap() + 1;
-                }
-            }
-        }
-        for row in grid {
-            for x in row {
-                if x != 0 {
-                    print!("{}", x);
-                } else {
-                    print!("x");
-                }
-            }
-            println!();
-        }
-    } else {
-        println!("No solution found!");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strategies_prune() {
+        let csp = over_constrained()[0].csp().unwrap();
+        let [chronological, backjumping, nogoods] = [
+            SearchStrategy::Chronological,
+            SearchStrategy::Backjumping,
+            SearchStrategy::BackjumpingWithNogoods,
+        ]
+        .map(|strategy| {
+            let (solution, stats) = csp.search(strategy);
+            assert_eq!(solution, None);
+            stats.nodes
+        });
+        assert!(backjumping < chronological);
+        assert!(nogoods < backjumping);
     }
 }
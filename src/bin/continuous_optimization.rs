@@ -0,0 +1,55 @@
+use std::env;
+use std::time::Instant;
+
+use classic_computer_science_problems::genetic_algorithm::continuous::cma_es::CmaEs;
+use classic_computer_science_problems::genetic_algorithm::continuous::differential_evolution::DifferentialEvolution;
+use classic_computer_science_problems::genetic_algorithm::continuous::evolution_strategy::EvolutionStrategy;
+use classic_computer_science_problems::genetic_algorithm::continuous::*;
+
+type Objective = fn(&[f64]) -> f64;
+
+/// (名前, 目的関数, 変数の範囲の端)
+const BENCHMARKS: [(&str, Objective, f64); 3] = [
+    ("Sphere", sphere, 5.0),
+    ("Rosenbrock", rosenbrock, 2.0),
+    ("Rastrigin", rastrigin, 5.12),
+];
+
+/// `continuous_optimization [dimension]`
+/// Sphere、Rosenbrock、Rastrigin の最小値をそれぞれの方法で探して比べる。
+fn main() {
+    let dimension = env::args()
+        .nth(1)
+        .and_then(|d| d.parse().ok())
+        .unwrap_or(10);
+    for (name, objective, limit) in BENCHMARKS {
+        let bounds = Bounds::uniform(dimension, -limit, limit);
+        let optimizers: [(&str, Box<dyn Optimizer>); 4] = [
+            (
+                "(15,100)-ES",
+                Box::new(EvolutionStrategy::comma(bounds.clone(), 15, 100, 1000).seed(0)),
+            ),
+            (
+                "(15+100)-ES",
+                Box::new(EvolutionStrategy::plus(bounds.clone(), 15, 100, 1000).seed(0)),
+            ),
+            ("CMA-ES", Box::new(CmaEs::new(bounds.clone(), 5000).seed(0))),
+            (
+                "DE",
+                Box::new(DifferentialEvolution::new(bounds, 10 * dimension, 2000).seed(0)),
+            ),
+        ];
+        println!("{} ({} dimensions)", name, dimension);
+        for (label, mut optimizer) in optimizers {
+            let start = Instant::now();
+            let solution = optimizer.minimize(&objective);
+            println!(
+                "  {:<12} {:>12.3e} after {:>7} evaluations ({:?})",
+                label,
+                solution.value,
+                solution.evaluations,
+                start.elapsed()
+            );
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::str::FromStr;
+use std::time::Instant;
+
+use classic_computer_science_problems::puzzle::crossword::Crossword;
+
+/// 引数を省略したときのテンプレートと辞書
+const TEMPLATE: &str = "crossword/grid7.txt";
+const DICTIONARY: &str = "crossword/words.txt";
+
+/// `crossword [TEMPLATE DICTIONARY]` で盤面のテンプレートを辞書の単語で埋める。
+/// テンプレートは `#` が黒マス、`.` が空きマスで、辞書は 1 行に 1 単語。
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let (template, dictionary) = match args.as_slice() {
+        [] => (TEMPLATE, DICTIONARY),
+        [template, dictionary] => (template.as_str(), dictionary.as_str()),
+        _ => return Err("usage: crossword [TEMPLATE DICTIONARY]".into()),
+    };
+    let crossword = Crossword::from_str(&fs::read_to_string(template)?)?;
+    let dictionary = Crossword::parse_dictionary(&fs::read_to_string(dictionary)?);
+    println!("{}", crossword);
+
+    let start = Instant::now();
+    match crossword.fill(&dictionary) {
+        Ok(Some(filled)) => println!("{}", filled),
+        Ok(None) => println!("No solution found!"),
+        Err(e) => println!("Invalid template: {}", e),
+    }
+    println!("{:?}", start.elapsed());
+    Ok(())
+}
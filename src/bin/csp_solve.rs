@@ -0,0 +1,53 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::str::FromStr;
+
+use classic_computer_science_problems::csp::model::Model;
+use serde_json::{Map, Value};
+
+/// `csp_solve [--json] [file]` でテキスト形式の CSP を読み込んで解く。
+/// ファイルを省略すると標準入力から読み込む。
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut json = false;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ if arg.starts_with('-') || path.is_some() => {
+                return Err("usage: csp_solve [--json] [FILE]".into())
+            }
+            _ => path = Some(arg),
+        }
+    }
+    let source = match path {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source)?;
+            source
+        }
+    };
+
+    let model = Model::from_str(&source)?;
+    let solution = model.csp()?.backtracking_search();
+    match solution {
+        Some(solution) if json => {
+            let object = model
+                .variables()
+                .iter()
+                .map(|variable| (variable.clone(), Value::from(solution[variable])))
+                .collect::<Map<_, _>>();
+            println!("{}", Value::Object(object));
+        }
+        Some(solution) => {
+            for variable in model.variables() {
+                println!("{} = {}", variable, solution[variable]);
+            }
+        }
+        None if json => println!("null"),
+        None => println!("No solution found!"),
+    }
+    Ok(())
+}
@@ -24,13 +24,13 @@ impl Eq for DijkstraNode {}
 
 impl PartialOrd for DijkstraNode {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.distance.partial_cmp(&other.distance)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for DijkstraNode {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        self.distance.partial_cmp(&other.distance).unwrap()
     }
 }
 
@@ -63,11 +63,11 @@ fn dijkstra<V: PartialEq + Clone>(
 
 fn distance_array_to_vertex_dict<V: PartialEq + Clone + Hash + Eq>(
     wg: &WeightedGraph<V>,
-    distances: &Vec<Option<f64>>,
+    distances: &[Option<f64>],
 ) -> HashMap<V, Option<f64>> {
     let mut result = HashMap::new();
-    for i in 0..distances.len() {
-        result.insert(wg.vertex_at(i).clone(), distances[i]);
+    for (i, distance) in distances.iter().enumerate() {
+        result.insert(wg.vertex_at(i).clone(), *distance);
     }
     result
 }
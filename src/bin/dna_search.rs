@@ -35,7 +35,7 @@ impl FromStr for Gene {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() % 3 != 0 {
+        if !s.len().is_multiple_of(3) {
             return Err("Invalid gene length");
         }
         let mut codons = Vec::new();
@@ -1,10 +1,14 @@
+use std::env;
+
 use classic_computer_science_problems::kmeans;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 #[derive(Debug)]
+#[allow(dead_code)]
 struct Govenor<'a>(f64, f64, &'a str);
 
-fn main() {
-    let governors = vec![
+fn governors() -> Vec<Govenor<'static>> {
+    vec![
         Govenor(-86.79113, 72.0, "Alabama"),
         Govenor(-152.404419, 66.0, "Alaska"),
         Govenor(-111.431221, 53.0, "Arizona"),
@@ -55,21 +59,48 @@ fn main() {
         Govenor(-80.954453, 66.0, "West Virginia"),
         Govenor(-89.616508, 49.0, "Wisconsin"),
         Govenor(-107.30249, 55.0, "Wyoming"),
-    ];
+    ]
+}
+
+/// 経度と年齢で 2 つに分け、クラスタごとの添字を返す。
+fn cluster<R: Rng>(governors: &[Govenor], rng: &mut R) -> Vec<Vec<usize>> {
     let mut kmeans = kmeans::KMeans::new(
         2,
         governors
             .iter()
             .map(|g| kmeans::DataPoint::new(vec![g.0, g.1]))
             .collect(),
+        rng,
     );
-    let clusters = kmeans.run(100);
-    for (i, cluster) in clusters.iter().enumerate() {
-        let cluster_governors = cluster
-            .points()
-            .iter()
-            .map(|i| &governors[*i])
-            .collect::<Vec<_>>();
+    kmeans
+        .run(100)
+        .iter()
+        .map(|cluster| cluster.points().to_vec())
+        .collect()
+}
+
+/// `govenors [seed]`。seed を与えると毎回同じクラスタになる。
+fn main() {
+    let mut rng = match env::args().nth(1).and_then(|seed| seed.parse().ok()) {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let governors = governors();
+    for (i, cluster) in cluster(&governors, &mut rng).iter().enumerate() {
+        let cluster_governors = cluster.iter().map(|i| &governors[*i]).collect::<Vec<_>>();
         println!("Cluster {}: {:?}", i, cluster_governors);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed() {
+        let governors = governors();
+        let first = cluster(&governors, &mut StdRng::seed_from_u64(7));
+        let second = cluster(&governors, &mut StdRng::seed_from_u64(7));
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,70 @@
+use std::env;
+use std::time::Instant;
+
+use classic_computer_science_problems::scheduling::{Scheduling, Task};
+
+/// Fisher と Thompson の 6x6 問題 (ft06)。ジョブごとに (機械, 処理時間) を順に並べる。
+/// 最適な makespan は 55。
+const FT06: [[(usize, usize); 6]; 6] = [
+    [(2, 1), (0, 3), (1, 6), (3, 7), (5, 3), (4, 6)],
+    [(1, 8), (2, 5), (4, 10), (5, 10), (0, 10), (3, 4)],
+    [(2, 5), (3, 4), (5, 8), (0, 9), (1, 1), (4, 7)],
+    [(1, 5), (0, 5), (2, 5), (3, 3), (4, 8), (5, 9)],
+    [(2, 9), (1, 3), (4, 5), (5, 4), (0, 3), (3, 1)],
+    [(1, 3), (3, 3), (5, 9), (0, 10), (4, 4), (2, 1)],
+];
+
+/// すべての作業を順に行っても収まる期限
+const HORIZON: usize = 197;
+
+fn job_shop(horizon: usize) -> Scheduling {
+    let mut scheduling = Scheduling::new(horizon);
+    for (job, operations) in FT06.iter().enumerate() {
+        let mut previous = None;
+        for (machine, duration) in operations {
+            let task = scheduling.add_task(Task::new(
+                &job.to_string(),
+                *duration,
+                &[&format!("machine {}", machine)],
+            ));
+            if let Some(previous) = previous {
+                scheduling.add_precedence(previous, task).unwrap();
+            }
+            previous = Some(task);
+        }
+    }
+    scheduling
+}
+
+/// ft06 を期限内に収める。期限を省略すると makespan が最小の日程を探す。
+/// ガントチャートの数字はジョブの番号。
+fn main() {
+    let horizon = env::args().nth(1).and_then(|h| h.parse().ok());
+    let start = Instant::now();
+    let schedule = match horizon {
+        Some(horizon) => job_shop(horizon).solve(),
+        None => job_shop(HORIZON).minimize(),
+    };
+    match schedule {
+        Ok(Some(schedule)) => {
+            print!("{}", schedule);
+            println!("makespan {} ({:?})", schedule.makespan(), start.elapsed());
+        }
+        Ok(None) => println!("No schedule within {}", horizon.unwrap_or(HORIZON)),
+        Err(e) => println!("No schedule within {}: {}", horizon.unwrap_or(HORIZON), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ft06() {
+        let schedule = job_shop(60).minimize().unwrap().unwrap();
+        assert_eq!(schedule.makespan(), 55);
+        let schedule = job_shop(57).solve().unwrap().unwrap();
+        assert!(schedule.makespan() <= 57);
+        assert_eq!(job_shop(54).solve(), Ok(None));
+    }
+}
@@ -1,3 +1,7 @@
+use classic_computer_science_problems::genetic_algorithm::nsga2::*;
+use classic_computer_science_problems::genetic_algorithm::operators::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
 #[allow(dead_code)]
 #[derive(Debug)]
 struct Item<'a> {
@@ -31,63 +35,121 @@ fn knapsack<'a>(items: &'a [Item], max_capacity: usize) -> Vec<&'a Item<'a>> {
     solution
 }
 
+const ITEMS: [Item; 11] = [
+    Item {
+        name: "television",
+        weight: 50,
+        value: 500.0,
+    },
+    Item {
+        name: "candlesticks",
+        weight: 2,
+        value: 300.0,
+    },
+    Item {
+        name: "stereo",
+        weight: 35,
+        value: 400.0,
+    },
+    Item {
+        name: "laptop",
+        weight: 3,
+        value: 1000.0,
+    },
+    Item {
+        name: "food",
+        weight: 15,
+        value: 50.0,
+    },
+    Item {
+        name: "clothing",
+        weight: 20,
+        value: 800.0,
+    },
+    Item {
+        name: "jewelry",
+        weight: 1,
+        value: 4000.0,
+    },
+    Item {
+        name: "books",
+        weight: 100,
+        value: 300.0,
+    },
+    Item {
+        name: "printer",
+        weight: 18,
+        value: 30.0,
+    },
+    Item {
+        name: "refrigerator",
+        weight: 200,
+        value: 700.0,
+    },
+    Item {
+        name: "painting",
+        weight: 10,
+        value: 1000.0,
+    },
+];
+
+/// どの品物を入れるか。価値を大きく、重さを小さくする 2 つの目的をもつ。
+#[derive(Debug, Clone)]
+struct Packed(Vec<bool>);
+
+impl Packed {
+    fn items(&self) -> Vec<&'static Item<'static>> {
+        ITEMS
+            .iter()
+            .zip(&self.0)
+            .filter(|(_, taken)| **taken)
+            .map(|(item, _)| item)
+            .collect()
+    }
+}
+
+impl MultiObjective for Packed {
+    fn objectives(&self) -> Vec<f64> {
+        let items = self.items();
+        vec![
+            items.iter().fold(0.0, |sum, item| sum + item.value as f64),
+            -(items.iter().map(|item| item.weight).sum::<usize>() as f64),
+        ]
+    }
+}
+
+impl Genome for Packed {
+    type Gene = bool;
+
+    fn genes(&self) -> &[bool] {
+        &self.0
+    }
+
+    fn genes_mut(&mut self) -> &mut [bool] {
+        &mut self.0
+    }
+}
+
+/// 容量 75 の動的計画法の解と、価値と重さのパレート前線を NSGA-II で求めたものを表示する。
 fn main() {
-    let items = vec![
-        Item {
-            name: "television",
-            weight: 50,
-            value: 500.0,
-        },
-        Item {
-            name: "candlesticks",
-            weight: 2,
-            value: 300.0,
-        },
-        Item {
-            name: "stereo",
-            weight: 35,
-            value: 400.0,
-        },
-        Item {
-            name: "laptop",
-            weight: 3,
-            value: 1000.0,
-        },
-        Item {
-            name: "food",
-            weight: 15,
-            value: 50.0,
-        },
-        Item {
-            name: "clothing",
-            weight: 20,
-            value: 800.0,
-        },
-        Item {
-            name: "jewelry",
-            weight: 1,
-            value: 4000.0,
-        },
-        Item {
-            name: "books",
-            weight: 100,
-            value: 300.0,
-        },
-        Item {
-            name: "printer",
-            weight: 18,
-            value: 30.0,
-        },
-        Item {
-            name: "refrigerator",
-            weight: 200,
-            value: 700.0,
-        },
-        Item {
-            name: "painting",
-            weight: 10,
-            value: 1000.0,
-        },
-    ];
-    println!("{:?}", knapsack(&items, 75));
+    println!("{:?}", knapsack(&ITEMS, 75));
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let population = (0..40)
+        .map(|_| Packed((0..ITEMS.len()).map(|_| rng.gen_bool(0.5)).collect()))
+        .collect();
+    let mut front = Nsga2::new(population, 100, 0.5, 0.9, UniformCrossover, FlipMutation)
+        .seed(rng.gen())
+        .run();
+    front.sort_by_key(|packed| packed.objectives()[1] as i64);
+    println!("Pareto front (value, weight):");
+    for packed in front.iter().rev() {
+        let objectives = packed.objectives();
+        let names = packed
+            .items()
+            .iter()
+            .map(|item| item.name)
+            .collect::<Vec<_>>();
+        println!("  {:>7} {:>4} {:?}", objectives[0], -objectives[1], names);
+    }
 }
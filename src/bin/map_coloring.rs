@@ -17,7 +17,7 @@ impl<'a> Constraint<&'a str, &'a str> for MapColoringConstraint<'a> {
     }
 }
 
-fn main() {
+fn main() -> Result<(), CspError<&'static str>> {
     let variables = vec![
         "Western Australia",
         "Northern Territory",
@@ -31,30 +31,38 @@ fn main() {
         .iter()
         .map(|variable| (*variable, vec!["red", "green", "blue"]))
         .collect();
-    let mut csp = CSP::new(variables, domains);
+    let mut csp = CSP::new(variables, domains)?;
     csp.add_constraint(MapColoringConstraint(
         "Western Australia",
         "Northern Territory",
-    ));
+    ))?;
     csp.add_constraint(MapColoringConstraint(
         "Western Australia",
         "South Australia",
-    ));
+    ))?;
     csp.add_constraint(MapColoringConstraint(
         "South Australia",
         "Northern Territory",
-    ));
-    csp.add_constraint(MapColoringConstraint("Queensland", "Northern Territory"));
-    csp.add_constraint(MapColoringConstraint("Queensland", "South Australia"));
-    csp.add_constraint(MapColoringConstraint("Queensland", "New South Wales"));
-    csp.add_constraint(MapColoringConstraint("New South Wales", "South Australia"));
-    csp.add_constraint(MapColoringConstraint("Victoria", "South Australia"));
-    csp.add_constraint(MapColoringConstraint("Victoria", "New South Wales"));
-    csp.add_constraint(MapColoringConstraint("Victoria", "Tasmania"));
+    ))?;
+    csp.add_constraint(MapColoringConstraint("Queensland", "Northern Territory"))?;
+    csp.add_constraint(MapColoringConstraint("Queensland", "South Australia"))?;
+    csp.add_constraint(MapColoringConstraint("Queensland", "New South Wales"))?;
+    csp.add_constraint(MapColoringConstraint("New South Wales", "South Australia"))?;
+    csp.add_constraint(MapColoringConstraint("Victoria", "South Australia"))?;
+    csp.add_constraint(MapColoringConstraint("Victoria", "New South Wales"))?;
+    csp.add_constraint(MapColoringConstraint("Victoria", "Tasmania"))?;
     let solution = csp.backtracking_search();
     if let Some(solution) = solution {
         println!("Solution: {:?}", solution);
     } else {
         println!("No solution found!");
     }
+    let colorings = csp.count_solutions(usize::MAX);
+    csp.add_interchangeable_values(vec!["red", "green", "blue"]);
+    println!(
+        "{} colorings, {} up to renaming colors",
+        colorings,
+        csp.count_solutions(usize::MAX)
+    );
+    Ok(())
 }
@@ -134,10 +134,10 @@ impl MazeBuilder {
 
         // fill in the blocked cells with a random sparseness
         let mut rng = thread_rng();
-        for row in 0..rows {
-            for column in 0..columns {
+        for row in grid.iter_mut() {
+            for cell in row.iter_mut() {
                 if rng.gen::<f64>() < sparseness {
-                    grid[row][column] = Cell::Blocked;
+                    *cell = Cell::Blocked;
                 }
             }
         }
@@ -16,7 +16,7 @@ impl<const MAX_NUM: usize> MCState<MAX_NUM> {
             wc: cannibals,
             em: MAX_NUM - missionaries,
             ec: MAX_NUM - cannibals,
-            boat: boat,
+            boat,
         }
     }
 
@@ -42,10 +42,8 @@ impl<const MAX_NUM: usize> MCState<MAX_NUM> {
     fn is_legal(&self) -> bool {
         if self.wm < self.wc && self.wm > 0 {
             false
-        } else if self.em < self.ec && self.em > 0 {
-            false
         } else {
-            true
+            !(self.em < self.ec && self.em > 0)
         }
     }
 
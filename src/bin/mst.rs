@@ -16,13 +16,13 @@ impl Eq for OrdEdge {}
 
 impl PartialOrd for OrdEdge {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.0.weight().partial_cmp(&other.0.weight())
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for OrdEdge {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(&other).unwrap()
+        self.0.weight().partial_cmp(&other.0.weight()).unwrap()
     }
 }
 
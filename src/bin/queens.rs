@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use std::env;
+use std::time::Instant;
 
 use classic_computer_science_problems::csp::*;
 
@@ -13,7 +15,7 @@ impl Constraint<usize, usize> for QueensConstraints {
 
     fn satisfied(&self, assignment: &HashMap<usize, usize>) -> bool {
         for (q1c, q1r) in assignment {
-            for q2c in (q1c + 1)..=self.columns.len() as usize {
+            for q2c in (q1c + 1)..=self.columns.len() {
                 if let Some(q2r) = assignment.get(&q2c) {
                     if q1r == q2r {
                         return false;
@@ -27,20 +29,110 @@ impl Constraint<usize, usize> for QueensConstraints {
         }
         true
     }
+
+    /// 行と 2 方向の斜めごとにクイーンを数えて、ほかのクイーンと重なる列を返す。
+    fn conflicted(&self, assignment: &HashMap<usize, usize>) -> Vec<usize> {
+        let n = self.columns.len();
+        let mut rows = vec![0; n + 1];
+        let mut diagonals = vec![0; 2 * n + 1];
+        let mut anti_diagonals = vec![0; 2 * n + 1];
+        for column in &self.columns {
+            let row = assignment[column];
+            rows[row] += 1;
+            diagonals[row + column] += 1;
+            anti_diagonals[row + n - column] += 1;
+        }
+        self.columns
+            .iter()
+            .filter(|column| {
+                let row = assignment[column];
+                rows[row] > 1
+                    || diagonals[row + *column] > 1
+                    || anti_diagonals[row + n - *column] > 1
+            })
+            .copied()
+            .collect()
+    }
+
+    /// ほかのクイーンが利いている行を数える。
+    fn conflicts(
+        &self,
+        variable: &usize,
+        domain: &[usize],
+        assignment: &HashMap<usize, usize>,
+    ) -> Vec<usize> {
+        let n = self.columns.len();
+        let mut attacks = vec![0; n + 1];
+        for column in self.columns.iter().filter(|c| *c != variable) {
+            let Some(row) = assignment.get(column).copied() else {
+                continue;
+            };
+            let distance = column.abs_diff(*variable);
+            attacks[row] += 1;
+            if row + distance <= n {
+                attacks[row + distance] += 1;
+            }
+            if row > distance {
+                attacks[row - distance] += 1;
+            }
+        }
+        domain.iter().map(|row| attacks[*row]).collect()
+    }
 }
 
-fn main() {
-    let columns = vec![1, 2, 3, 4, 5, 6, 7, 8];
+fn queens(n: usize) -> Result<CSP<usize, usize, QueensConstraints>, CspError<usize>> {
+    let columns = (1..=n).collect::<Vec<_>>();
     let mut rows = HashMap::new();
     for column in &columns {
-        rows.insert(column.clone(), columns.clone());
+        rows.insert(*column, columns.clone());
     }
-    let mut csp = CSP::new(columns.clone(), rows);
-    csp.add_constraint(QueensConstraints { columns });
-    let solution = csp.backtracking_search();
-    if let Some(solution) = solution {
-        println!("Solution: {:?}", solution);
-    } else {
-        println!("No solution found!");
+    let mut csp = CSP::new(columns.clone(), rows)?;
+    csp.add_constraint(QueensConstraints { columns })?;
+    Ok(csp)
+}
+
+/// 盤面の回転と反転。列と行の組を移す。
+fn add_board_symmetries(csp: &mut CSP<usize, usize, QueensConstraints>, n: usize) {
+    let m = n + 1;
+    csp.add_symmetry(move |c, r| (*r, m - c));
+    csp.add_symmetry(move |c, r| (m - c, m - r));
+    csp.add_symmetry(move |c, r| (m - r, *c));
+    csp.add_symmetry(move |c, r| (m - c, *r));
+    csp.add_symmetry(move |c, r| (*c, m - r));
+    csp.add_symmetry(move |c, r| (*r, *c));
+    csp.add_symmetry(move |c, r| (m - r, m - c));
+}
+
+/// 引数がなければ 8 クイーンをバックトラックで解き、解の個数を数える。
+/// `queens n` では n クイーンを最小衝突法で解く。
+fn main() -> Result<(), CspError<usize>> {
+    match env::args().nth(1).and_then(|n| n.parse().ok()) {
+        None => {
+            let mut csp = queens(8)?;
+            let solution = csp.backtracking_search();
+            if let Some(solution) = solution {
+                println!("Solution: {:?}", solution);
+            } else {
+                println!("No solution found!");
+            }
+            let solutions = csp.count_solutions(usize::MAX);
+            add_board_symmetries(&mut csp, 8);
+            println!(
+                "{} solutions, {} up to rotation and reflection",
+                solutions,
+                csp.count_solutions(usize::MAX)
+            );
+        }
+        Some(n) => {
+            let csp = queens(n)?;
+            let start = Instant::now();
+            let solution = csp.min_conflicts(10 * n, 10, &mut rand::thread_rng());
+            if solution.is_some() {
+                println!("Solved {} queens in {:?}", n, start.elapsed());
+            } else {
+                println!("No solution found!");
+            }
+        }
     }
+    Ok(())
 }
@@ -35,19 +35,20 @@ impl Constraint<char, usize> for SendMoreMoneyConstraint {
     }
 }
 
-fn main() {
+fn main() -> Result<(), CspError<char>> {
     let letters = vec!['S', 'E', 'N', 'D', 'M', 'O', 'R', 'Y'];
     let mut possible_digits = HashMap::new();
     for letter in letters.iter() {
         possible_digits.insert(*letter, Vec::from_iter(0..10));
     }
     possible_digits.insert('M', vec![1]);
-    let mut csp = CSP::new(letters.clone(), possible_digits);
-    csp.add_constraint(SendMoreMoneyConstraint { letters });
+    let mut csp = CSP::new(letters.clone(), possible_digits)?;
+    csp.add_constraint(SendMoreMoneyConstraint { letters })?;
     let solution = csp.backtracking_search();
     if let Some(solution) = solution {
         println!("Solution: {:?}", solution);
     } else {
         println!("No solution found!");
     }
+    Ok(())
 }
@@ -1,9 +1,11 @@
 use std::fmt;
 
+use classic_computer_science_problems::genetic_algorithm::island::*;
+use classic_computer_science_problems::genetic_algorithm::operators::*;
 use classic_computer_science_problems::genetic_algorithm::*;
 use rand::seq::index::sample;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{thread_rng, Rng};
 
 #[derive(Clone)]
 struct SendMoreMoney2 {
@@ -32,6 +34,18 @@ impl fmt::Display for SendMoreMoney2 {
     }
 }
 
+impl Genome for SendMoreMoney2 {
+    type Gene = char;
+
+    fn genes(&self) -> &[char] {
+        &self.letters
+    }
+
+    fn genes_mut(&mut self) -> &mut [char] {
+        &mut self.letters
+    }
+}
+
 impl Chromosome for SendMoreMoney2 {
     fn fitness(&self) -> f64 {
         let s = self.letters.iter().position(|&c| c == 'S').unwrap() as isize;
@@ -49,17 +63,17 @@ impl Chromosome for SendMoreMoney2 {
         1.0 / (diff as f64 + 1.0)
     }
 
-    fn random_instance() -> Self {
-        let mut rng = thread_rng();
-        let mut letters = vec!['S', 'E', 'N', 'D', 'M', 'O', 'R', 'Y'];
-        letters.shuffle(&mut rng);
+    fn random_instance<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        // 位置が数字を表すので、8 と 9 も使えるように空きの印を 2 つ加える
+        let mut letters = vec!['S', 'E', 'N', 'D', 'M', 'O', 'R', 'Y', '_', '-'];
+        letters.shuffle(rng);
         Self { letters }
     }
 
-    fn crossover(&self, other: &Self) -> (Self, Self) {
+    fn crossover<R: Rng + ?Sized>(&self, other: &Self, rng: &mut R) -> (Self, Self) {
         let mut child1 = self.clone();
         let mut child2 = other.clone();
-        let indices = sample(&mut thread_rng(), self.letters.len(), 2);
+        let indices = sample(rng, self.letters.len(), 2);
         let (idx1, idx2) = (indices.index(0), indices.index(1));
         let (l1, l2) = (child1.letters[idx1], child2.letters[idx2]);
         let i = child1.letters.iter().position(|&c| c == l2).unwrap();
@@ -69,27 +83,29 @@ impl Chromosome for SendMoreMoney2 {
         (child1, child2)
     }
 
-    fn mutate(&mut self) {
-        let indices = sample(&mut thread_rng(), self.letters.len(), 2);
+    fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let indices = sample(rng, self.letters.len(), 2);
         let (idx1, idx2) = (indices.index(0), indices.index(1));
         self.letters.swap(idx1, idx2);
     }
 }
 
+/// 1000 個体を 4 つの島に分け、10 世代ごとに上位 5 個を隣の島へ移住させる。
+/// 1 つの集団のままのルーレット選択よりも早く収束しにくい。
 fn main() {
     tracing_subscriber::fmt::init();
 
-    let initial_population = (0..1000)
-        .map(|_| SendMoreMoney2::random_instance())
-        .collect::<Vec<_>>();
-    let mut ga = GeneticAlgorithm::new(
-        initial_population,
-        1.0,
-        1000,
-        0.2,
-        0.7,
-        SelectionType::Roulette,
-    );
-    let result = ga.run();
-    println!("{:}", result);
+    let mut rng = thread_rng();
+    let islands = (0..4)
+        .map(|_| {
+            let initial_population = (0..250)
+                .map(|_| SendMoreMoney2::random_instance(&mut rng))
+                .collect::<Vec<_>>();
+            GeneticAlgorithm::new(initial_population, 1.0, 1000, 0.2, 0.7)
+                .selection(Roulette::default())
+                .crossover(PartiallyMappedCrossover)
+        })
+        .collect();
+    let (result, termination) = Islands::new(islands, Topology::Ring, 10, 5).run_parallel();
+    println!("{:} ({:?})", result, termination);
 }
@@ -1,7 +1,10 @@
+use std::env;
+
+use classic_computer_science_problems::genetic_algorithm::termination::Termination;
 use classic_computer_science_problems::genetic_algorithm::*;
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 struct SimpleEquation {
     x: i64,
     y: i64,
@@ -13,15 +16,14 @@ impl Chromosome for SimpleEquation {
         6.0 * self.x as f64 - self.x.pow(2) as f64 + 4.0 * self.y as f64 - self.y.pow(2) as f64
     }
 
-    fn random_instance() -> Self {
-        let mut rng = thread_rng();
+    fn random_instance<R: Rng + ?Sized>(rng: &mut R) -> Self {
         Self {
             x: rng.gen_range(0..100),
             y: rng.gen_range(0..100),
         }
     }
 
-    fn crossover(&self, other: &Self) -> (Self, Self) {
+    fn crossover<R: Rng + ?Sized>(&self, other: &Self, _rng: &mut R) -> (Self, Self) {
         (
             Self {
                 x: self.x,
@@ -34,8 +36,7 @@ impl Chromosome for SimpleEquation {
         )
     }
 
-    fn mutate(&mut self) {
-        let mut rng = thread_rng();
+    fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R) {
         if rng.gen_bool(0.5) {
             if rng.gen_bool(0.5) {
                 self.x += 1
@@ -52,26 +53,41 @@ impl Chromosome for SimpleEquation {
     }
 }
 
-fn main() {
-    tracing_subscriber::fmt::init();
-
+/// 初期集団も遺伝的アルゴリズムも seed から作るので、同じ seed なら同じ結果になる。
+fn solve(seed: u64) -> (SimpleEquation, Termination) {
+    let mut rng = StdRng::seed_from_u64(seed);
     let initial_population = (0..20)
-        .map(|_| SimpleEquation::random_instance())
+        .map(|_| SimpleEquation::random_instance(&mut rng))
         .collect::<Vec<_>>();
 
-    let mut ga = GeneticAlgorithm::new(
-        initial_population,
-        13.0,
-        100,
-        0.1,
-        0.7,
-        SelectionType::Tournament,
-    );
-    let result = ga.run();
+    let mut ga = GeneticAlgorithm::new(initial_population, 13.0, 100, 0.1, 0.7).seed(rng.gen());
+    ga.run()
+}
+
+/// `simple_equation [seed]`
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    let seed = env::args()
+        .nth(1)
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(|| rand::thread_rng().gen());
+    let (result, termination) = solve(seed);
     println!(
-        "X: {} Y: {} Fitness: {}",
+        "X: {} Y: {} Fitness: {} ({})",
         result.x,
         result.y,
-        result.fitness()
+        result.fitness(),
+        termination
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed() {
+        assert_eq!(solve(7), solve(7));
+    }
+}
@@ -0,0 +1,44 @@
+use std::env;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+use classic_computer_science_problems::puzzle::sudoku::Sudoku;
+
+fn solve(line: &str) {
+    match Sudoku::from_str(line) {
+        Ok(sudoku) => {
+            println!("{}", sudoku);
+            match sudoku.solve() {
+                Ok(Some(solution)) => println!("{}", solution),
+                Ok(None) => println!("No solution found!"),
+                Err(e) => println!("Invalid puzzle: {}", e),
+            }
+        }
+        Err(e) => println!("Invalid puzzle {:?}: {}", line, e),
+    }
+}
+
+/// `sudoku generate [order]` で一意解の問題を作り、
+/// それ以外では引数か標準入力の 1 行ずつを問題として解く。
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(String::as_str) == Some("generate") {
+        let order = args.get(1).and_then(|x| x.parse().ok()).unwrap_or(3);
+        match Sudoku::generate(order, &mut rand::thread_rng()) {
+            Ok(puzzle) => {
+                println!("{}", puzzle.to_line());
+                println!("{}", puzzle);
+                println!("{}", puzzle.solve().unwrap().unwrap());
+            }
+            Err(e) => println!("Invalid order {}: {}", order, e),
+        }
+    } else if args.is_empty() {
+        for line in io::stdin().lock().lines() {
+            solve(&line.unwrap());
+        }
+    } else {
+        for line in &args {
+            solve(line);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::sync::OnceLock;
+
+use classic_computer_science_problems::genetic_algorithm::genetic_programming::symbolic_regression::*;
+use classic_computer_science_problems::genetic_algorithm::genetic_programming::*;
+use classic_computer_science_problems::genetic_algorithm::operators::{Elitism, Tournament};
+use classic_computer_science_problems::genetic_algorithm::termination::Stagnation;
+use classic_computer_science_problems::genetic_algorithm::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// wine.csv の列。最初の列は品種。
+const COLUMNS: [&str; 14] = [
+    "class",
+    "alcohol",
+    "malic_acid",
+    "ash",
+    "alcalinity",
+    "magnesium",
+    "phenols",
+    "flavanoids",
+    "nonflavanoid_phenols",
+    "proanthocyanins",
+    "color_intensity",
+    "hue",
+    "od280_od315",
+    "proline",
+];
+
+static PROBLEM: OnceLock<SymbolicRegression> = OnceLock::new();
+
+struct Wine;
+
+impl RegressionProblem for Wine {
+    fn get() -> &'static SymbolicRegression {
+        PROBLEM.get().unwrap()
+    }
+}
+
+fn read_wine() -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(File::open("wine.csv")?);
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        rows.push(
+            record?
+                .iter()
+                .map(|x| x.parse())
+                .collect::<Result<Vec<f64>, _>>()?,
+        );
+    }
+    Ok(rows)
+}
+
+/// `symbolic_regression [column]`
+/// 列の名前を渡すと、その列をほかの測定値の式で表す。
+/// 渡さなければ alcohol * hue - flavanoids の列を作り、その式を見つけ直す。
+fn main() -> Result<(), Box<dyn Error>> {
+    tracing_subscriber::fmt::init();
+
+    let rows = read_wine()?;
+    let column = |name: &str| COLUMNS.iter().position(|c| *c == name).unwrap();
+    let (target, variables, targets): (String, Vec<&str>, Vec<f64>) = match env::args().nth(1) {
+        Some(name) => {
+            let target = COLUMNS
+                .iter()
+                .position(|c| *c == name)
+                .ok_or(format!("unknown column {}", name))?;
+            let variables = COLUMNS[1..]
+                .iter()
+                .copied()
+                .filter(|c| *c != name)
+                .collect();
+            (
+                name,
+                variables,
+                rows.iter().map(|row| row[target]).collect(),
+            )
+        }
+        None => {
+            let (alcohol, hue, flavanoids) =
+                (column("alcohol"), column("hue"), column("flavanoids"));
+            let targets = rows
+                .iter()
+                .map(|row| row[alcohol] * row[hue] - row[flavanoids])
+                .collect();
+            (
+                "alcohol * hue - flavanoids".to_string(),
+                COLUMNS[1..].to_vec(),
+                targets,
+            )
+        }
+    };
+    let inputs = rows
+        .iter()
+        .map(|row| variables.iter().map(|v| row[column(v)]).collect())
+        .collect();
+    let primitives = Primitives::new(&variables).constants(Some((-10.0, 10.0)));
+    PROBLEM
+        .set(SymbolicRegression::new(primitives, inputs, targets))
+        .unwrap();
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let population = Program::<Wine>::population(1000, &mut rng);
+    let mut ga = GeneticAlgorithm::new(population, -0.01, 200, 0.2, 0.9)
+        .selection(Tournament::new(7))
+        .replacement(Elitism::new(5))
+        .stop_when(Stagnation::new(50))
+        .seed(1);
+    let (best, termination) = ga.run();
+    println!(
+        "{} ≈ {}",
+        target,
+        best.tree.display(Wine::get().primitives().variables())
+    );
+    println!(
+        "MSE {:.6} with {} nodes ({})",
+        Wine::get().mean_squared_error(&best.tree),
+        best.tree.len(),
+        termination
+    );
+    Ok(())
+}
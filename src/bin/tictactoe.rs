@@ -6,7 +6,7 @@ use std::io::Write;
 
 fn get_player_move(board: &TTTBoard) -> <TTTBoard as Board>::Move {
     let mut player_move = None;
-    while player_move.map_or(true, |x| !board.legal_moves().contains(&x)) {
+    while player_move.is_none_or(|x| !board.legal_moves().contains(&x)) {
         print!("Enter a legal square (0-8): ");
         io::stdout().flush().unwrap();
         let mut input = String::new();
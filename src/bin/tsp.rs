@@ -1,70 +1,63 @@
-use itertools::Itertools;
-use lazy_static::lazy_static;
-use maplit::hashmap;
-use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::str::FromStr;
+use std::time::Instant;
 
-lazy_static! {
-    static ref VT_DISTANCES: HashMap<&'static str, HashMap<&'static str, u32>> = hashmap! {
-        "Rutland" => hashmap!{
-            "Burlington" => 67,
-            "White River Junction" => 46,
-            "Bennington" => 55,
-            "Brattleboro" => 75},
-        "Burlington" => hashmap!{
-            "Rutland" => 67,
-            "White River Junction" => 91,
-            "Bennington" => 122,
-            "Brattleboro" => 153},
-        "White River Junction" => hashmap!{
-            "Rutland" => 46,
-            "Burlington" => 91,
-            "Bennington" => 98,
-            "Brattleboro" => 65},
-        "Bennington" => hashmap!{
-            "Rutland" => 55,
-            "Burlington" => 122,
-            "White River Junction" => 98,
-            "Brattleboro" => 40},
-        "Brattleboro" => hashmap!{
-            "Rutland" => 75,
-            "Burlington" => 153,
-            "White River Junction" => 65,
-            "Bennington" => 40}
-    };
-    static ref VT_CITIES: Vec<&'static str> = VT_DISTANCES.keys().cloned().collect();
-    static ref CITY_PERMUTATIONS: Vec<Vec<&'static str>> = VT_CITIES
-        .iter()
-        .cloned()
-        .permutations(VT_CITIES.len())
-        .collect();
-    static ref TSP_PATHS: Vec<Vec<&'static str>> = CITY_PERMUTATIONS
-        .iter()
-        .map(|path| {
-            let mut path = path.clone();
-            path.push(path[0]);
-            path
-        })
-        .collect();
-}
+use classic_computer_science_problems::tsp::genetic::GeneticTsp;
+use classic_computer_science_problems::tsp::{exact, Instance, Tour};
+
+/// tsplib にある問題と、その最適な巡回路の長さ
+const BUNDLED: [(&str, i64); 3] = [
+    ("tsplib/vermont5.tsp", 318),
+    ("tsplib/gr17.tsp", 2085),
+    ("tsplib/berlin52.tsp", 7542),
+];
+
+/// 厳密解法でも解く都市の数の上限。Held–Karp の表が収まる大きさにする。
+const EXACT_LIMIT: usize = exact::HELD_KARP_LIMIT;
 
-fn main() {
-    let mut best_path = None;
-    let mut min_distance = u32::MAX;
-    for path in TSP_PATHS.iter() {
-        let mut distance = 0;
-        let mut last = path[0];
-        for next in path.iter().skip(1) {
-            distance += VT_DISTANCES[last][next];
-            last = next;
+type Solver = fn(&Instance) -> Tour;
+
+const EXACT: [(&str, Solver); 2] = [
+    ("Held-Karp", exact::held_karp),
+    ("branch and bound", exact::branch_and_bound),
+];
+
+/// `tsp [file [optimum]]`
+/// TSPLIB 形式の問題を遺伝的アルゴリズムと局所探索で解く。
+/// 都市が EXACT_LIMIT 以下なら厳密解法でも解き、その長さを最適値として比べる。
+/// ファイルを省略すると tsplib の問題を解き、最適値との差を示す。
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let problems = match args.first() {
+        Some(path) => vec![(path.as_str(), args.get(1).map(|o| o.parse()).transpose()?)],
+        None => BUNDLED
+            .iter()
+            .map(|(path, optimum)| (*path, Some(*optimum)))
+            .collect(),
+    };
+    for (path, optimum) in problems {
+        let instance = Instance::from_str(&fs::read_to_string(path)?)?;
+        println!("{}: {} cities", instance.name(), instance.dimension());
+        let mut optimum = optimum;
+        if instance.dimension() <= EXACT_LIMIT {
+            for (name, solve) in EXACT {
+                let start = Instant::now();
+                let tour = solve(&instance);
+                println!("  {}: length {} ({:?})", name, tour.length, start.elapsed());
+                optimum = optimum.or(Some(tour.length));
+            }
+            println!("  1-tree bound: {}", exact::one_tree_bound(&instance));
         }
-        if distance < min_distance {
-            min_distance = distance;
-            best_path = Some(path);
+        let start = Instant::now();
+        let tour = GeneticTsp::new(&instance, 50, 100).seed(0).run();
+        print!("  genetic: length {} ({:?})", tour.length, start.elapsed());
+        match optimum {
+            Some(optimum) => println!(", optimum {}, gap {:.2}%", optimum, tour.gap(optimum)),
+            None => println!(),
         }
+        println!("  {:?}", tour.cities);
     }
-    println!(
-        "The shortest path is {:?} in {} miles.",
-        best_path.unwrap(),
-        min_distance
-    );
+    Ok(())
 }
@@ -122,6 +122,12 @@ pub struct C4Board {
     turn: C4Piece,
 }
 
+impl Default for C4Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl C4Board {
     pub fn new() -> Self {
         Self {
@@ -134,7 +140,7 @@ impl C4Board {
         for i in 0..NUM_COLUMNS {
             print!("{} ", i);
         }
-        println!("");
+        println!();
         for r in (0..NUM_ROWS).rev() {
             for c in 0..NUM_COLUMNS {
                 print!(
@@ -1,10 +1,150 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::hash::Hash;
-use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use itertools::Itertools;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+pub mod model;
+
+/// CSP の構築時に検出される不正な入力
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CspError<V> {
+    /// ドメインが割り当てられていない変数
+    MissingDomain(V),
+    /// 値を 1 つも持たないドメイン
+    EmptyDomain(V),
+    /// 2 回以上現れる変数
+    DuplicateVariable(V),
+    /// CSP に含まれない変数を参照する制約
+    UnknownVariable(V),
+    /// 変数を 1 つも持たない制約
+    EmptyConstraint,
+}
+
+impl<V: fmt::Debug> fmt::Display for CspError<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CspError::MissingDomain(v) => write!(f, "variable {:?} has no domain", v),
+            CspError::EmptyDomain(v) => write!(f, "domain of variable {:?} is empty", v),
+            CspError::DuplicateVariable(v) => write!(f, "variable {:?} is defined twice", v),
+            CspError::UnknownVariable(v) => {
+                write!(f, "variable {:?} in constraint is not in the CSP", v)
+            }
+            CspError::EmptyConstraint => write!(f, "constraint has no variables"),
+        }
+    }
+}
+
+impl<V: fmt::Debug> std::error::Error for CspError<V> {}
 
 pub trait Constraint<V, D> {
     fn variables(&self) -> Vec<V>;
     fn satisfied(&self, arguments: &HashMap<V, D>) -> bool;
+
+    /// すべての変数が割り当てられているとき、制約を破る原因になっている変数。
+    /// 既定では満たされていなければすべての変数を返す。
+    fn conflicted(&self, assignment: &HashMap<V, D>) -> Vec<V> {
+        if self.satisfied(assignment) {
+            Vec::new()
+        } else {
+            self.variables()
+        }
+    }
+
+    /// variable を domain の各値に変えたときの違反の数。
+    /// ほかの変数は割り当てられていないこともあり、その変数の分は数えない。
+    /// 既定では値ごとに satisfied で確かめて、破られれば 1 とする。
+    /// 局所探索を速くしたいときは上書きする。
+    fn conflicts(&self, variable: &V, domain: &[D], assignment: &HashMap<V, D>) -> Vec<usize>
+    where
+        V: Eq + Hash + Clone,
+        D: Clone,
+    {
+        let mut assignment = assignment.clone();
+        domain
+            .iter()
+            .map(|value| {
+                assignment.insert(variable.clone(), value.clone());
+                usize::from(!self.satisfied(&assignment))
+            })
+            .collect()
+    }
+
+    /// ドメインから、この制約をどう割り当てても満たせない値を取り除く。
+    /// 空になったドメインがあれば false を返す。
+    /// 既定では、値が 1 つに決まっていない変数が 1 つだけのとき、その変数の値を satisfied で確かめる。
+    /// 値の範囲から推論できる制約は、SearchStrategy::Propagation を速くするために上書きする。
+    fn propagate(&self, domains: &mut HashMap<V, Vec<D>>) -> bool
+    where
+        V: Eq + Hash + Clone,
+        D: Clone,
+    {
+        let mut assignment = HashMap::new();
+        let mut open = None;
+        for variable in self.variables() {
+            match domains[&variable].as_slice() {
+                [value] => {
+                    assignment.insert(variable, value.clone());
+                }
+                _ if open.is_none() => open = Some(variable),
+                _ => return true,
+            }
+        }
+        let Some(variable) = open else {
+            return self.satisfied(&assignment);
+        };
+        let values = domains.get_mut(&variable).unwrap();
+        values.retain(|value| {
+            assignment.insert(variable.clone(), value.clone());
+            self.satisfied(&assignment)
+        });
+        !values.is_empty()
+    }
+}
+
+/// search で使う探索の方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchStrategy {
+    /// 失敗したら直前の変数に戻る
+    #[default]
+    Chronological,
+    /// 失敗の原因になった変数まで一気に戻る（conflict-directed backjumping）
+    Backjumping,
+    /// Backjumping に加えて、失敗した部分割り当てを nogood として覚えておき、二度と試さない
+    BackjumpingWithNogoods,
+    /// Constraint::propagate を変わらなくなるまで繰り返してドメインを狭め、
+    /// 候補の最も少ない変数のドメインを前半と後半に分けて探す
+    Propagation,
+}
+
+/// search で調べた節点（変数に値を割り当てようとした回数）と、覚えた nogood の数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchStats {
+    pub nodes: usize,
+    pub nogoods: usize,
+}
+
+/// 変数の位置と値の組で表した、解に含まれない部分割り当て
+type Nogood<D> = Vec<(usize, D)>;
+
+/// 変数の並びと割り当てを受け取り、割り当てが対称なものの代表になり得るかを判定する
+type SymmetryBreaker<V, D> = Box<dyn Fn(&[V], &HashMap<V, D>) -> bool + Send + Sync>;
+
+/// add_constraint で加えた制約を remove_constraint で取り除くための識別子
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConstraintId(usize);
+
+/// repair で見つけた解と、前の解から値が変わった変数
+#[derive(Debug, Clone)]
+pub struct Repair<V, D> {
+    pub solution: HashMap<V, D>,
+    /// CSP に加えた順
+    pub changed: Vec<V>,
 }
 
 pub struct CSP<V, D, C>
@@ -15,7 +155,10 @@ where
 {
     variables: Vec<V>,
     domains: HashMap<V, Vec<D>>,
-    constraints: HashMap<V, Vec<Rc<C>>>,
+    constraints: HashMap<V, Vec<Arc<C>>>,
+    constraint_ids: HashMap<ConstraintId, Arc<C>>,
+    next_constraint_id: usize,
+    symmetry_breakers: Vec<SymmetryBreaker<V, D>>,
 }
 
 impl<V, D, C> CSP<V, D, C>
@@ -24,38 +167,95 @@ where
     D: Eq + Clone,
     C: Constraint<V, D>,
 {
-    pub fn new(variables: Vec<V>, domains: HashMap<V, Vec<D>>) -> Self {
+    pub fn new(variables: Vec<V>, domains: HashMap<V, Vec<D>>) -> Result<Self, CspError<V>> {
         let mut constraints = HashMap::new();
         for variable in &variables {
-            constraints.insert(variable.clone(), Vec::new());
-            if !domains.contains_key(variable) {
-                panic!("Every variable should have a domain assigned to it.");
+            match domains.get(variable) {
+                None => return Err(CspError::MissingDomain(variable.clone())),
+                Some(domain) if domain.is_empty() => {
+                    return Err(CspError::EmptyDomain(variable.clone()))
+                }
+                Some(_) => {}
+            }
+            if constraints.insert(variable.clone(), Vec::new()).is_some() {
+                return Err(CspError::DuplicateVariable(variable.clone()));
             }
         }
-        Self {
+        Ok(Self {
             variables,
             domains,
             constraints,
+            constraint_ids: HashMap::new(),
+            next_constraint_id: 0,
+            symmetry_breakers: Vec::new(),
+        })
+    }
+
+    pub fn add_constraint(&mut self, constraint: C) -> Result<ConstraintId, CspError<V>> {
+        let variables = constraint.variables();
+        if variables.is_empty() {
+            return Err(CspError::EmptyConstraint);
+        }
+        // 途中で失敗しても CSP が変更されないように、先にすべての変数を検証する
+        if let Some(variable) = variables
+            .iter()
+            .find(|v| !self.constraints.contains_key(*v))
+        {
+            return Err(CspError::UnknownVariable(variable.clone()));
         }
+        let constraint = Arc::from(constraint);
+        let mut seen = HashSet::new();
+        for variable in variables {
+            if seen.insert(variable.clone()) {
+                self.constraints
+                    .get_mut(&variable)
+                    .unwrap()
+                    .push(constraint.clone());
+            }
+        }
+        let id = ConstraintId(self.next_constraint_id);
+        self.next_constraint_id += 1;
+        self.constraint_ids.insert(id, constraint);
+        Ok(id)
     }
 
-    pub fn add_constraint(&mut self, constraint: C) {
-        let constraint = Rc::from(constraint);
+    /// 制約を取り除く。すでに取り除かれていれば false を返す。
+    pub fn remove_constraint(&mut self, id: ConstraintId) -> bool {
+        let Some(constraint) = self.constraint_ids.remove(&id) else {
+            return false;
+        };
         for variable in constraint.variables() {
-            if !self.variables.contains(&variable) {
-                panic!("Variable in constraint not in CSP");
+            if let Some(constraints) = self.constraints.get_mut(&variable) {
+                constraints.retain(|c| !Arc::ptr_eq(c, &constraint));
             }
-            self.constraints
-                .get_mut(&variable)
-                .unwrap()
-                .push(constraint.clone());
         }
+        true
+    }
+
+    /// 変数のドメインを置き換える。
+    pub fn set_domain(&mut self, variable: V, domain: Vec<D>) -> Result<(), CspError<V>> {
+        if !self.constraints.contains_key(&variable) {
+            return Err(CspError::UnknownVariable(variable));
+        }
+        if domain.is_empty() {
+            return Err(CspError::EmptyDomain(variable));
+        }
+        self.domains.insert(variable, domain);
+        Ok(())
     }
 
     pub fn consistent(&self, variable: &V, assignment: &HashMap<V, D>) -> bool {
         self.constraints[variable]
             .iter()
             .all(|c| c.satisfied(assignment))
+            && self.breaks_symmetry(assignment)
+    }
+
+    /// 宣言された対称性について、割り当てが代表になり得るか
+    fn breaks_symmetry(&self, assignment: &HashMap<V, D>) -> bool {
+        self.symmetry_breakers
+            .iter()
+            .all(|breaker| breaker(&self.variables, assignment))
     }
 
     pub fn backtracking_search(&self) -> Option<HashMap<V, D>> {
@@ -85,4 +285,788 @@ where
         }
         None
     }
+
+    /// strategy で探索して、解と探索の統計を返す。
+    /// Propagation のほかは、変数を CSP に加えた順に割り当てる。
+    pub fn search(&self, strategy: SearchStrategy) -> (Option<HashMap<V, D>>, SearchStats) {
+        let mut stats = SearchStats::default();
+        if strategy == SearchStrategy::Propagation {
+            let result = self.split(self.domains.clone(), &mut stats);
+            return (result, stats);
+        }
+        // 一番深い変数の位置ごとに nogood を持つ。その変数に値を入れるときにだけ調べればよい。
+        let mut nogoods = HashMap::new();
+        let mut assignment = HashMap::new();
+        let result = self.backjump(0, strategy, &mut assignment, &mut nogoods, &mut stats);
+        (result.ok().map(|()| assignment), stats)
+    }
+
+    /// 位置 index 以降の変数を割り当てる。
+    /// 失敗したときは、失敗の原因になった index より前の変数の位置を返す。
+    fn backjump(
+        &self,
+        index: usize,
+        strategy: SearchStrategy,
+        assignment: &mut HashMap<V, D>,
+        nogoods: &mut HashMap<usize, Vec<Nogood<D>>>,
+        stats: &mut SearchStats,
+    ) -> Result<(), HashSet<usize>> {
+        let Some(variable) = self.variables.get(index) else {
+            return Ok(());
+        };
+        let mut conflict = HashSet::new();
+        for value in &self.domains[variable] {
+            stats.nodes += 1;
+            assignment.insert(variable.clone(), value.clone());
+            let culprits = if strategy == SearchStrategy::Chronological {
+                (!self.consistent(variable, assignment)).then(HashSet::new)
+            } else {
+                self.explain(index, assignment)
+                    .or_else(|| self.violated_nogood(index, assignment, nogoods))
+                    // 対称性で枝刈りした原因はわからないので、前の変数すべてとする
+                    .or_else(|| (!self.breaks_symmetry(assignment)).then(|| (0..index).collect()))
+            };
+            if let Some(culprits) = culprits {
+                conflict.extend(culprits);
+                continue;
+            }
+            match self.backjump(index + 1, strategy, assignment, nogoods, stats) {
+                Ok(()) => return Ok(()),
+                // 子の失敗に自分が関わっていなければ、ほかの値を試しても無駄なので飛び越す
+                Err(culprits)
+                    if strategy != SearchStrategy::Chronological
+                        && culprits.iter().all(|i| *i < index) =>
+                {
+                    assignment.remove(variable);
+                    return Err(culprits);
+                }
+                Err(culprits) => conflict.extend(culprits.into_iter().filter(|i| *i < index)),
+            }
+        }
+        assignment.remove(variable);
+
+        if strategy == SearchStrategy::Chronological {
+            // 原因を区別せず、直前の変数に戻る
+            return Err(index.checked_sub(1).into_iter().collect());
+        }
+        if strategy == SearchStrategy::BackjumpingWithNogoods {
+            if let Some(deepest) = conflict.iter().max() {
+                let nogood = conflict
+                    .iter()
+                    .sorted()
+                    .map(|i| (*i, assignment[&self.variables[*i]].clone()))
+                    .collect();
+                nogoods.entry(*deepest).or_default().push(nogood);
+                stats.nogoods += 1;
+            }
+        }
+        Err(conflict)
+    }
+
+    /// 位置 index の変数の割り当てで破られた制約があれば、その原因になった前の変数の位置を返す。
+    /// 制約の中身はわからないので、前の変数を順に加えていって初めて破られた変数を原因に加え、
+    /// 原因だけで破られるようになるまで繰り返す。
+    fn explain(&self, index: usize, assignment: &HashMap<V, D>) -> Option<HashSet<usize>> {
+        let variable = &self.variables[index];
+        let constraint = self.constraints[variable]
+            .iter()
+            .find(|c| !c.satisfied(assignment))?;
+        let scope = constraint.variables().into_iter().collect::<HashSet<_>>();
+        let mut culprits = HashSet::new();
+        let mut explanation = HashMap::new();
+        explanation.insert(variable.clone(), assignment[variable].clone());
+        while constraint.satisfied(&explanation) {
+            let mut partial = explanation.clone();
+            let culprit = self.variables[..index].iter().position(|other| {
+                if !scope.contains(other) || culprits.contains(other) {
+                    return false;
+                }
+                partial.insert(other.clone(), assignment[other].clone());
+                !constraint.satisfied(&partial)
+            });
+            let Some(culprit) = culprit else {
+                // 1 つずつ加えても破られないときは、範囲内の前の変数すべてを原因とする
+                return Some(
+                    (0..index)
+                        .filter(|i| scope.contains(&self.variables[*i]))
+                        .collect(),
+                );
+            };
+            let other = &self.variables[culprit];
+            culprits.insert(other.clone());
+            explanation.insert(other.clone(), assignment[other].clone());
+        }
+        Some(
+            (0..index)
+                .filter(|i| culprits.contains(&self.variables[*i]))
+                .collect(),
+        )
+    }
+
+    /// 位置 index の変数の割り当てで nogood と一致してしまったら、その nogood のほかの変数の位置を返す。
+    fn violated_nogood(
+        &self,
+        index: usize,
+        assignment: &HashMap<V, D>,
+        nogoods: &HashMap<usize, Vec<Nogood<D>>>,
+    ) -> Option<HashSet<usize>> {
+        let nogood = nogoods.get(&index)?.iter().find(|nogood| {
+            nogood
+                .iter()
+                .all(|(i, value)| assignment[&self.variables[*i]] == *value)
+        })?;
+        Some(
+            nogood
+                .iter()
+                .map(|(i, _)| *i)
+                .filter(|i| *i < index)
+                .collect(),
+        )
+    }
+
+    /// 制約やドメインを変えたあとで、前の解 previous からできるだけ少ない変数を変えて解き直す
+    /// （minimal-perturbation repair）。変えてよい変数の数を 0 から 1 つずつ増やして探す。
+    pub fn repair(&self, previous: &HashMap<V, D>) -> Option<Repair<V, D>> {
+        for limit in 0..=self.variables.len() {
+            let mut assignment = HashMap::new();
+            if self.repair_within(0, previous, limit, &mut assignment) {
+                let changed = self
+                    .variables
+                    .iter()
+                    .filter(|v| previous.get(*v) != assignment.get(*v))
+                    .cloned()
+                    .collect();
+                return Some(Repair {
+                    solution: assignment,
+                    changed,
+                });
+            }
+        }
+        None
+    }
+
+    /// 位置 index 以降の変数を、前の解から limit 個までしか変えずに割り当てる。
+    fn repair_within(
+        &self,
+        index: usize,
+        previous: &HashMap<V, D>,
+        limit: usize,
+        assignment: &mut HashMap<V, D>,
+    ) -> bool {
+        let Some(variable) = self.variables.get(index) else {
+            return true;
+        };
+        let domain = &self.domains[variable];
+        let kept = previous
+            .get(variable)
+            .filter(|value| domain.contains(value));
+        // 前の値を先に試す
+        let values = kept
+            .into_iter()
+            .chain(domain.iter().filter(|value| Some(*value) != kept));
+        for value in values {
+            let cost = usize::from(Some(value) != kept);
+            if cost > limit {
+                break;
+            }
+            assignment.insert(variable.clone(), value.clone());
+            if self.consistent(variable, assignment)
+                && self.repair_within(index + 1, previous, limit - cost, assignment)
+            {
+                return true;
+            }
+        }
+        assignment.remove(variable);
+        false
+    }
+
+    /// domains を propagate で狭め、値が 1 つに決まっていない変数のドメインを半分に分けて探す。
+    fn split(
+        &self,
+        mut domains: HashMap<V, Vec<D>>,
+        stats: &mut SearchStats,
+    ) -> Option<HashMap<V, D>> {
+        stats.nodes += 1;
+        if !self.propagate(&mut domains) {
+            return None;
+        }
+        // 候補の数が同じなら CSP に加えた順
+        let Some(variable) = self
+            .variables
+            .iter()
+            .filter(|v| domains[*v].len() > 1)
+            .min_by_key(|v| domains[*v].len())
+        else {
+            let assignment = domains
+                .into_iter()
+                .map(|(variable, mut values)| (variable, values.remove(0)))
+                .collect::<HashMap<_, _>>();
+            return self
+                .variables
+                .iter()
+                .all(|v| self.consistent(v, &assignment))
+                .then_some(assignment);
+        };
+        let values = &domains[variable];
+        let (low, high) = values.split_at(values.len() / 2);
+        [low.to_vec(), high.to_vec()].into_iter().find_map(|part| {
+            let mut domains = domains.clone();
+            domains.insert(variable.clone(), part);
+            self.split(domains, stats)
+        })
+    }
+
+    /// すべての制約の propagate を、ドメインが変わらなくなるまで繰り返す。
+    fn propagate(&self, domains: &mut HashMap<V, Vec<D>>) -> bool {
+        let size = |domains: &HashMap<V, Vec<D>>| domains.values().map(Vec::len).sum::<usize>();
+        loop {
+            let before = size(domains);
+            if !self
+                .constraint_ids
+                .values()
+                .all(|constraint| constraint.propagate(domains))
+            {
+                return false;
+            }
+            if size(domains) == before {
+                return true;
+            }
+        }
+    }
+
+    /// 解の個数を数える。limit 個見つかった時点で探索を打ち切る。
+    /// 対称性を宣言していれば、対称な解はまとめて 1 つと数える。
+    /// 値を入れるたびに、制約を共有するまだ割り当てていない変数のドメインから矛盾する値を除き
+    /// （forward checking）、候補の最も少ない変数から割り当てる。
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        let mut count = 0;
+        let domains = self.domains.clone();
+        self.count(&mut HashMap::new(), domains, limit, &mut count);
+        count
+    }
+
+    /// domains はまだ割り当てていない変数の、これまでの割り当てと矛盾しない値
+    fn count(
+        &self,
+        assignment: &mut HashMap<V, D>,
+        domains: HashMap<V, Vec<D>>,
+        limit: usize,
+        count: &mut usize,
+    ) {
+        if *count >= limit {
+            return;
+        }
+        // 候補の数が同じなら CSP に加えた順
+        let Some(variable) = self
+            .variables
+            .iter()
+            .filter(|v| !assignment.contains_key(*v))
+            .min_by_key(|v| domains[*v].len())
+        else {
+            *count += 1;
+            return;
+        };
+        for value in &domains[variable] {
+            assignment.insert(variable.clone(), value.clone());
+            if self.consistent(variable, assignment) {
+                if let Some(pruned) = self.forward_check(variable, assignment, &domains) {
+                    self.count(assignment, pruned, limit, count);
+                }
+            }
+            assignment.remove(variable);
+            if *count >= limit {
+                return;
+            }
+        }
+    }
+
+    /// variable に値を入れたあと、制約を共有するまだ割り当てていない変数のドメインから
+    /// その制約を破る値を除く。空になったドメインがあれば None を返す。
+    /// ほかの制約は variable の値で結果が変わらないので調べ直さない。
+    fn forward_check(
+        &self,
+        variable: &V,
+        assignment: &mut HashMap<V, D>,
+        domains: &HashMap<V, Vec<D>>,
+    ) -> Option<HashMap<V, Vec<D>>> {
+        let mut pruned = domains.clone();
+        pruned.remove(variable);
+        for constraint in &self.constraints[variable] {
+            for neighbour in constraint.variables() {
+                // 割り当て済みの変数は domains に残っていない
+                let Some(values) = pruned.get_mut(&neighbour) else {
+                    continue;
+                };
+                values.retain(|value| {
+                    assignment.insert(neighbour.clone(), value.clone());
+                    let satisfied = constraint.satisfied(assignment);
+                    assignment.remove(&neighbour);
+                    satisfied
+                });
+                if values.is_empty() {
+                    return None;
+                }
+            }
+        }
+        Some(pruned)
+    }
+
+    /// 最小衝突法による局所探索。変数を順に違反が最も少ない値に割り当ててから、
+    /// 制約を破っている変数を 1 つ選び、違反が最も少ない値に変えることを繰り返す。
+    /// max_steps 回で解が見つからなければ、max_restarts 回までやり直す。
+    pub fn min_conflicts<R: Rng>(
+        &self,
+        max_steps: usize,
+        max_restarts: usize,
+        rng: &mut R,
+    ) -> Option<HashMap<V, D>> {
+        // 複数の変数に登録された制約を 1 回ずつ調べるために重複を除く
+        let mut seen = HashSet::new();
+        let constraints = self
+            .variables
+            .iter()
+            .flat_map(|v| &self.constraints[v])
+            .filter(|c| seen.insert(Arc::as_ptr(c)))
+            .collect::<Vec<_>>();
+
+        for _ in 0..=max_restarts {
+            let mut assignment = HashMap::new();
+            for variable in &self.variables {
+                let value = self.min_conflicts_value(variable, &assignment, rng);
+                assignment.insert(variable.clone(), value);
+            }
+            for step in 0..=max_steps {
+                let conflicted = constraints
+                    .iter()
+                    .flat_map(|c| c.conflicted(&assignment))
+                    .unique()
+                    .collect::<Vec<_>>();
+                let Some(variable) = conflicted.choose(rng) else {
+                    return Some(assignment);
+                };
+                if step == max_steps {
+                    break;
+                }
+                let value = self.min_conflicts_value(variable, &assignment, rng);
+                assignment.insert(variable.clone(), value);
+            }
+        }
+        None
+    }
+
+    fn min_conflicts_value<R: Rng>(
+        &self,
+        variable: &V,
+        assignment: &HashMap<V, D>,
+        rng: &mut R,
+    ) -> D {
+        let domain = &self.domains[variable];
+        let mut conflicts = vec![0; domain.len()];
+        for constraint in &self.constraints[variable] {
+            let counts = constraint.conflicts(variable, domain, assignment);
+            for (total, count) in conflicts.iter_mut().zip(counts) {
+                *total += count;
+            }
+        }
+        // 同じ数の候補からはランダムに選んで、同じ割り当てを行き来しないようにする
+        let min = *conflicts.iter().min().unwrap();
+        let candidates = conflicts
+            .iter()
+            .positions(|count| *count == min)
+            .collect::<Vec<_>>();
+        domain[*candidates.choose(rng).unwrap()].clone()
+    }
+}
+
+/// 複数のスレッドでの探索
+impl<V, D, C> CSP<V, D, C>
+where
+    V: Eq + Hash + Clone + Send + Sync,
+    D: Eq + Clone + Send + Sync,
+    C: Constraint<V, D> + Send + Sync,
+{
+    /// threads 個のスレッドで、変数と値の順番を変えたバックトラックを同時に走らせる。
+    /// 最初のスレッドは backtracking_search と同じ順番で、ほかは seed から決まる乱数で並べ替える。
+    /// どれかが解を見つけたら、ほかのスレッドは打ち切る。threads が 0 なら 1 とする。
+    pub fn parallel_search(&self, threads: usize, seed: u64) -> Option<HashMap<V, D>> {
+        let stop = AtomicBool::new(false);
+        let solution = Mutex::new(None);
+        thread::scope(|scope| {
+            for i in 0..threads.max(1) {
+                let (stop, solution) = (&stop, &solution);
+                scope.spawn(move || {
+                    let mut order = self
+                        .variables
+                        .iter()
+                        .map(|v| (v, self.domains[v].iter().collect::<Vec<_>>()))
+                        .collect::<Vec<_>>();
+                    if i > 0 {
+                        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+                        order.shuffle(&mut rng);
+                        for (_, domain) in order.iter_mut() {
+                            domain.shuffle(&mut rng);
+                        }
+                    }
+                    let mut assignment = HashMap::new();
+                    if self.backtrack_ordered(&order, &mut assignment, stop) {
+                        solution.lock().unwrap().get_or_insert(assignment);
+                        stop.store(true, AtomicOrdering::Relaxed);
+                    }
+                });
+            }
+        });
+        solution.into_inner().unwrap()
+    }
+
+    /// order の順に変数を割り当てる。stop が立ったら諦める。
+    fn backtrack_ordered(
+        &self,
+        order: &[(&V, Vec<&D>)],
+        assignment: &mut HashMap<V, D>,
+        stop: &AtomicBool,
+    ) -> bool {
+        let Some(((variable, domain), rest)) = order.split_first() else {
+            return true;
+        };
+        for value in domain {
+            if stop.load(AtomicOrdering::Relaxed) {
+                return false;
+            }
+            assignment.insert((*variable).clone(), (*value).clone());
+            if self.consistent(variable, assignment)
+                && self.backtrack_ordered(rest, assignment, stop)
+            {
+                return true;
+            }
+        }
+        assignment.remove(*variable);
+        false
+    }
+}
+
+/// 対称性の宣言。backtracking_search と search は変数を CSP に加えた順に割り当てて、
+/// 対称な割り当てのうち代表になるものだけを調べる。count_solutions は割り当てる順が変わるので
+/// 枝刈りは弱くなるが、すべての変数が決まったところで確かめるため数える解は同じになる。
+/// 宣言した対称性が群をなしていれば（恒等変換以外のすべての変換を宣言していれば）、
+/// 対称な解のまとまりごとにちょうど 1 つの解が残る。
+impl<V, D, C> CSP<V, D, C>
+where
+    V: Eq + Hash + Clone + Send + Sync + 'static,
+    D: Eq + Clone + Send + Sync + 'static,
+    C: Constraint<V, D>,
+{
+    /// values の値は互いに入れ替えても解が解のままになる（地図の色など）。
+    /// `values[i]` は `values[i - 1]` がそれより前の変数に現れるまで使わない。
+    pub fn add_interchangeable_values(&mut self, values: Vec<D>) {
+        self.symmetry_breakers
+            .push(Box::new(move |variables, assignment| {
+                // 先頭から何個目までの値が現れたか
+                let mut used = 0;
+                for variable in variables {
+                    let Some(value) = assignment.get(variable) else {
+                        return true;
+                    };
+                    match values.iter().position(|v| v == value) {
+                        Some(i) if i > used => return false,
+                        Some(i) if i == used => used += 1,
+                        _ => {}
+                    }
+                }
+                true
+            }));
+    }
+
+    /// 変数と値の組を symmetry で移しても解が解のままになる（盤面の回転や反転など）。
+    /// 変数の順に値を並べた列が、移した割り当ての列より辞書順で大きくならないものだけを残す
+    /// （lex-leader 制約）。
+    pub fn add_symmetry<F>(&mut self, symmetry: F)
+    where
+        D: Ord,
+        F: Fn(&V, &D) -> (V, D) + Send + Sync + 'static,
+    {
+        self.symmetry_breakers
+            .push(Box::new(move |variables, assignment| {
+                let image = assignment
+                    .iter()
+                    .map(|(variable, value)| symmetry(variable, value))
+                    .collect::<HashMap<_, _>>();
+                for variable in variables {
+                    match (assignment.get(variable), image.get(variable)) {
+                        (Some(value), Some(moved)) => match value.cmp(moved) {
+                            Ordering::Less => return true,
+                            Ordering::Greater => return false,
+                            Ordering::Equal => {}
+                        },
+                        // ここから先はまだ決まらない
+                        _ => return true,
+                    }
+                }
+                true
+            }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NotEqual(&'static str, &'static str);
+
+    impl Constraint<&'static str, u8> for NotEqual {
+        fn variables(&self) -> Vec<&'static str> {
+            vec![self.0, self.1]
+        }
+
+        fn satisfied(&self, assignment: &HashMap<&'static str, u8>) -> bool {
+            match (assignment.get(self.0), assignment.get(self.1)) {
+                (Some(a), Some(b)) => a != b,
+                _ => true,
+            }
+        }
+    }
+
+    struct NoVariables;
+
+    impl Constraint<&'static str, u8> for NoVariables {
+        fn variables(&self) -> Vec<&'static str> {
+            Vec::new()
+        }
+
+        fn satisfied(&self, _assignment: &HashMap<&'static str, u8>) -> bool {
+            true
+        }
+    }
+
+    fn domains(variables: &[&'static str]) -> HashMap<&'static str, Vec<u8>> {
+        variables.iter().map(|v| (*v, vec![1, 2])).collect()
+    }
+
+    #[test]
+    fn test_new_missing_domain() {
+        let mut domains = domains(&["a"]);
+        domains.remove("a");
+        let result = CSP::<_, _, NotEqual>::new(vec!["a"], domains);
+        assert_eq!(result.err(), Some(CspError::MissingDomain("a")));
+    }
+
+    #[test]
+    fn test_new_empty_domain() {
+        let mut domains = domains(&["a", "b"]);
+        domains.insert("b", Vec::new());
+        let result = CSP::<_, _, NotEqual>::new(vec!["a", "b"], domains);
+        assert_eq!(result.err(), Some(CspError::EmptyDomain("b")));
+    }
+
+    #[test]
+    fn test_new_duplicate_variable() {
+        let result = CSP::<_, _, NotEqual>::new(vec!["a", "b", "a"], domains(&["a", "b"]));
+        assert_eq!(result.err(), Some(CspError::DuplicateVariable("a")));
+    }
+
+    #[test]
+    fn test_add_constraint_unknown_variable() {
+        let mut csp = CSP::new(vec!["a", "b"], domains(&["a", "b"])).unwrap();
+        assert_eq!(
+            csp.add_constraint(NotEqual("a", "c")),
+            Err(CspError::UnknownVariable("c"))
+        );
+        // 失敗した制約は一部だけ登録されたりしない
+        assert!(csp.constraints["a"].is_empty());
+    }
+
+    #[test]
+    fn test_add_constraint_without_variables() {
+        let mut csp = CSP::new(vec!["a"], domains(&["a"])).unwrap();
+        assert_eq!(
+            csp.add_constraint(NoVariables),
+            Err(CspError::EmptyConstraint)
+        );
+    }
+
+    #[test]
+    fn test_backtracking_search() {
+        let mut csp = CSP::new(vec!["a", "b"], domains(&["a", "b"])).unwrap();
+        csp.add_constraint(NotEqual("a", "b")).unwrap();
+        let solution = csp.backtracking_search().unwrap();
+        assert_ne!(solution["a"], solution["b"]);
+    }
+
+    #[test]
+    fn test_min_conflicts() {
+        let variables = vec!["a", "b", "c", "d"];
+        let mut csp = CSP::new(variables.clone(), domains(&variables)).unwrap();
+        for (x, y) in variables.iter().tuple_windows() {
+            csp.add_constraint(NotEqual(x, y)).unwrap();
+        }
+        let mut rng = StdRng::seed_from_u64(29);
+        let solution = csp.min_conflicts(100, 0, &mut rng).unwrap();
+        for (x, y) in variables.iter().tuple_windows() {
+            assert_ne!(solution[x], solution[y]);
+        }
+    }
+
+    #[test]
+    fn test_min_conflicts_unsatisfiable() {
+        // 3 つの変数を 2 色で塗り分けることはできない
+        let mut csp = CSP::new(vec!["a", "b", "c"], domains(&["a", "b", "c"])).unwrap();
+        csp.add_constraint(NotEqual("a", "b")).unwrap();
+        csp.add_constraint(NotEqual("b", "c")).unwrap();
+        csp.add_constraint(NotEqual("c", "a")).unwrap();
+        let mut rng = StdRng::seed_from_u64(29);
+        assert_eq!(csp.min_conflicts(50, 2, &mut rng), None);
+    }
+
+    const STRATEGIES: [SearchStrategy; 3] = [
+        SearchStrategy::Chronological,
+        SearchStrategy::Backjumping,
+        SearchStrategy::BackjumpingWithNogoods,
+    ];
+
+    #[test]
+    fn test_search() {
+        let variables = vec!["a", "b", "c", "d"];
+        let mut csp = CSP::new(variables.clone(), domains(&variables)).unwrap();
+        for (x, y) in variables.iter().tuple_windows() {
+            csp.add_constraint(NotEqual(x, y)).unwrap();
+        }
+        for strategy in STRATEGIES.into_iter().chain([SearchStrategy::Propagation]) {
+            let (solution, _) = csp.search(strategy);
+            let solution = solution.unwrap();
+            for (x, y) in variables.iter().tuple_windows() {
+                assert_ne!(solution[x], solution[y]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_backjumping() {
+        // d は a としか関係しないので、d で失敗したら b と c を飛び越して a に戻れる
+        let mut domains = domains(&["b", "c"]);
+        domains.insert("a", vec![1]);
+        domains.insert("d", vec![1]);
+        let mut csp = CSP::new(vec!["a", "b", "c", "d"], domains).unwrap();
+        csp.add_constraint(NotEqual("a", "d")).unwrap();
+        csp.add_constraint(NotEqual("b", "c")).unwrap();
+        let [chronological, backjumping, nogoods] = STRATEGIES.map(|strategy| {
+            let (solution, stats) = csp.search(strategy);
+            assert_eq!(solution, None);
+            stats
+        });
+        assert_eq!(chronological.nodes, 9);
+        assert_eq!(backjumping.nodes, 5);
+        assert_eq!(nogoods.nodes, 5);
+        assert_eq!(nogoods.nogoods, 1);
+        // a と d はどちらも値が 1 つしかないので、伝播だけで矛盾がわかる
+        let (solution, stats) = csp.search(SearchStrategy::Propagation);
+        assert_eq!(solution, None);
+        assert_eq!(stats.nodes, 1);
+    }
+
+    #[test]
+    fn test_remove_constraint() {
+        let mut csp = CSP::new(vec!["a", "b"], domains(&["a", "b"])).unwrap();
+        let id = csp.add_constraint(NotEqual("a", "b")).unwrap();
+        assert_eq!(csp.count_solutions(usize::MAX), 2);
+        assert!(csp.remove_constraint(id));
+        assert!(!csp.remove_constraint(id));
+        assert_eq!(csp.count_solutions(usize::MAX), 4);
+    }
+
+    #[test]
+    fn test_set_domain() {
+        let mut csp = CSP::<_, _, NotEqual>::new(vec!["a"], domains(&["a"])).unwrap();
+        assert_eq!(
+            csp.set_domain("a", Vec::new()),
+            Err(CspError::EmptyDomain("a"))
+        );
+        assert_eq!(
+            csp.set_domain("b", vec![1]),
+            Err(CspError::UnknownVariable("b"))
+        );
+        csp.set_domain("a", vec![2]).unwrap();
+        assert_eq!(csp.backtracking_search(), Some(HashMap::from([("a", 2)])));
+    }
+
+    #[test]
+    fn test_repair() {
+        let variables = vec!["a", "b", "c", "d"];
+        let mut domains = domains(&variables);
+        for domain in domains.values_mut() {
+            domain.push(3);
+        }
+        let mut csp = CSP::new(variables.clone(), domains).unwrap();
+        for (x, y) in variables.iter().tuple_windows() {
+            csp.add_constraint(NotEqual(x, y)).unwrap();
+        }
+        let previous = HashMap::from([("a", 1), ("b", 2), ("c", 1), ("d", 2)]);
+        assert_eq!(csp.repair(&previous).unwrap().changed, Vec::<&str>::new());
+
+        let id = csp.add_constraint(NotEqual("a", "c")).unwrap();
+        let repair = csp.repair(&previous).unwrap();
+        assert_eq!(repair.changed, ["c"]);
+        assert_eq!(repair.solution["c"], 3);
+
+        csp.remove_constraint(id);
+        csp.set_domain("b", vec![1, 3]).unwrap();
+        let repair = csp.repair(&previous).unwrap();
+        // b を 3 に変えれば c と d はそのままでよい
+        assert_eq!(repair.changed, ["b"]);
+        assert_eq!(repair.solution["b"], 3);
+    }
+
+    #[test]
+    fn test_parallel_search() {
+        let variables = vec!["a", "b", "c", "d"];
+        let mut csp = CSP::new(variables.clone(), domains(&variables)).unwrap();
+        for (x, y) in variables.iter().tuple_windows() {
+            csp.add_constraint(NotEqual(x, y)).unwrap();
+        }
+        let solution = csp.parallel_search(4, 32).unwrap();
+        for (x, y) in variables.iter().tuple_windows() {
+            assert_ne!(solution[x], solution[y]);
+        }
+        assert_eq!(csp.parallel_search(0, 32), csp.backtracking_search());
+
+        csp.add_constraint(NotEqual("a", "c")).unwrap();
+        assert_eq!(csp.parallel_search(4, 32), None);
+    }
+
+    #[test]
+    fn test_interchangeable_values() {
+        // 三角形を 3 色で塗る方法は 6 通りだが、色を入れ替えたものを除くと 1 通り
+        let mut domains = domains(&["a", "b", "c"]);
+        for domain in domains.values_mut() {
+            domain.push(3);
+        }
+        let mut csp = CSP::new(vec!["a", "b", "c"], domains).unwrap();
+        csp.add_constraint(NotEqual("a", "b")).unwrap();
+        csp.add_constraint(NotEqual("b", "c")).unwrap();
+        csp.add_constraint(NotEqual("c", "a")).unwrap();
+        assert_eq!(csp.count_solutions(usize::MAX), 6);
+        csp.add_interchangeable_values(vec![1, 2, 3]);
+        assert_eq!(csp.count_solutions(usize::MAX), 1);
+        for strategy in STRATEGIES {
+            let solution = csp.search(strategy).0.unwrap();
+            assert_eq!((solution["a"], solution["b"], solution["c"]), (1, 2, 3));
+        }
+    }
+
+    #[test]
+    fn test_symmetry() {
+        let mut csp = CSP::new(vec!["a", "b"], domains(&["a", "b"])).unwrap();
+        csp.add_constraint(NotEqual("a", "b")).unwrap();
+        // a と b を入れ替える
+        csp.add_symmetry(|variable, value| (if *variable == "a" { "b" } else { "a" }, *value));
+        assert_eq!(csp.count_solutions(usize::MAX), 1);
+        let solution = csp.backtracking_search().unwrap();
+        assert_eq!((solution["a"], solution["b"]), (1, 2));
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let mut csp = CSP::new(vec!["a", "b"], domains(&["a", "b"])).unwrap();
+        csp.add_constraint(NotEqual("a", "b")).unwrap();
+        assert_eq!(csp.count_solutions(usize::MAX), 2);
+        assert_eq!(csp.count_solutions(1), 1);
+    }
 }
@@ -0,0 +1,521 @@
+//! Rust を書かずに CSP を定義するためのテキスト形式
+//!
+//! 1 行に 1 つずつ、整数の変数と制約を並べる。`#` 以降はコメント。
+//!
+//! ```text
+//! var S E N D O R Y in 0..9
+//! var M in 1
+//! var x in 1..3,7
+//! alldifferent S E N D M O R Y
+//! constraint 1000*S + 100*E + 10*N + D + 1000*M + 100*O + 10*R + E = 10000*M + 1000*O + 100*N + 10*E + Y
+//! ```
+//!
+//! `constraint` には線形式どうしの比較（`=`, `!=`, `<`, `<=`, `>`, `>=`）を書ける。
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use itertools::Itertools;
+
+use super::{Constraint, CspError, CSP};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Relation {
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "=" | "==" => Some(Relation::Eq),
+            "!=" => Some(Relation::Ne),
+            "<" => Some(Relation::Lt),
+            "<=" => Some(Relation::Le),
+            ">" => Some(Relation::Gt),
+            ">=" => Some(Relation::Ge),
+            _ => None,
+        }
+    }
+
+    fn holds(&self, lhs: i128, rhs: i128) -> bool {
+        match self {
+            Relation::Eq => lhs == rhs,
+            Relation::Ne => lhs != rhs,
+            Relation::Lt => lhs < rhs,
+            Relation::Le => lhs <= rhs,
+            Relation::Gt => lhs > rhs,
+            Relation::Ge => lhs >= rhs,
+        }
+    }
+
+    /// 和が min..=max のどこかに決まるとき、まだ満たせる可能性があるか
+    fn possible(&self, min: i128, max: i128, constant: i128) -> bool {
+        match self {
+            Relation::Eq => min <= constant && constant <= max,
+            Relation::Ne => min != max || min != constant,
+            Relation::Lt => min < constant,
+            Relation::Le => min <= constant,
+            Relation::Gt => max > constant,
+            Relation::Ge => max >= constant,
+        }
+    }
+}
+
+/// テキスト形式で書ける制約
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelConstraint {
+    /// 変数の値がすべて異なる
+    AllDifferent(Vec<String>),
+    /// 係数と変数の積の和と constant が relation を満たす
+    Linear {
+        terms: Vec<(i64, String)>,
+        relation: Relation,
+        constant: i64,
+        /// 各項が取り得る値の最小値と最大値。CSP を作るときにドメインから求める。
+        bounds: Vec<(i128, i128)>,
+    },
+}
+
+impl Constraint<String, i64> for ModelConstraint {
+    fn variables(&self) -> Vec<String> {
+        match self {
+            ModelConstraint::AllDifferent(variables) => variables.clone(),
+            ModelConstraint::Linear { terms, .. } => {
+                terms.iter().map(|(_, variable)| variable.clone()).collect()
+            }
+        }
+    }
+
+    fn satisfied(&self, assignment: &HashMap<String, i64>) -> bool {
+        match self {
+            ModelConstraint::AllDifferent(variables) => variables
+                .iter()
+                .filter_map(|v| assignment.get(v))
+                .all_unique(),
+            ModelConstraint::Linear {
+                terms,
+                relation,
+                constant,
+                bounds,
+            } => {
+                // 値の入っていない項は取り得る範囲で見積もって枝刈りする
+                let (mut min, mut max) = (0i128, 0i128);
+                for (i, (coefficient, variable)) in terms.iter().enumerate() {
+                    match (assignment.get(variable), bounds.get(i)) {
+                        (Some(value), _) => {
+                            min += *coefficient as i128 * *value as i128;
+                            max += *coefficient as i128 * *value as i128;
+                        }
+                        (None, Some((low, high))) => {
+                            min += low;
+                            max += high;
+                        }
+                        (None, None) => return true,
+                    }
+                }
+                if min == max {
+                    relation.holds(min, *constant as i128)
+                } else {
+                    relation.possible(min, max, *constant as i128)
+                }
+            }
+        }
+    }
+}
+
+/// テキスト形式を読み込むときのエラー。行番号は 1 から数える。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseModelError {
+    /// 解釈できない行
+    InvalidLine(usize),
+    /// 解釈できない語句
+    InvalidToken(usize, String),
+    /// 値が MAX_DOMAIN_SIZE 個より多いドメイン
+    DomainTooLarge(usize, String),
+}
+
+impl fmt::Display for ParseModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseModelError::InvalidLine(line) => write!(f, "line {}: invalid line", line),
+            ParseModelError::InvalidToken(line, token) => {
+                write!(f, "line {}: invalid token {:?}", line, token)
+            }
+            ParseModelError::DomainTooLarge(line, domain) => write!(
+                f,
+                "line {}: domain {:?} has more than {} values",
+                line, domain, MAX_DOMAIN_SIZE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseModelError {}
+
+/// テキスト形式から読み込んだ CSP の定義
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Model {
+    variables: Vec<String>,
+    domains: HashMap<String, Vec<i64>>,
+    constraints: Vec<ModelConstraint>,
+}
+
+impl Model {
+    /// 定義された順の変数
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    pub fn constraints(&self) -> &[ModelConstraint] {
+        &self.constraints
+    }
+
+    /// 変数の重複や未定義の変数は CSP の構築時に検出する。
+    /// `constraint 1 = 2` のように項が消えた制約は定数どうしの比較として評価し、
+    /// 成り立てば捨て、成り立たなければ解のない CSP にする。
+    pub fn csp(&self) -> Result<CSP<String, i64, ModelConstraint>, CspError<String>> {
+        let mut csp = CSP::new(self.variables.clone(), self.domains.clone())?;
+        for constraint in &self.constraints {
+            let mut constraint = constraint.clone();
+            if let ModelConstraint::Linear {
+                terms,
+                relation,
+                constant,
+                bounds,
+            } = &mut constraint
+            {
+                if terms.is_empty() {
+                    if relation.holds(0, *constant as i128) {
+                        continue;
+                    }
+                    // 係数 0 の項で最初の変数に結びつける。この制約はどの値でも破られる。
+                    if let Some(variable) = self.variables.first() {
+                        terms.push((0, variable.clone()));
+                    }
+                }
+                for (coefficient, variable) in terms.iter() {
+                    let domain = self
+                        .domains
+                        .get(variable)
+                        .ok_or_else(|| CspError::UnknownVariable(variable.clone()))?;
+                    let (low, high) = domain
+                        .iter()
+                        .map(|value| *coefficient as i128 * *value as i128)
+                        .minmax()
+                        .into_option()
+                        .ok_or_else(|| CspError::EmptyDomain(variable.clone()))?;
+                    bounds.push((low, high));
+                }
+            }
+            csp.add_constraint(constraint)?;
+        }
+        Ok(csp)
+    }
+}
+
+fn is_identifier(token: &str) -> bool {
+    let mut chars = token.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 1 つの変数のドメインに並べられる値の数。大きな範囲をそのまま展開しないように制限する。
+pub const MAX_DOMAIN_SIZE: usize = 1 << 20;
+
+/// `1..3,7` のような範囲と値の並び
+fn parse_domain(s: &str, line: usize) -> Result<Vec<i64>, ParseModelError> {
+    let invalid = || ParseModelError::InvalidToken(line, s.to_string());
+    let mut domain = Vec::new();
+    for part in s.split(',') {
+        match part.split_once("..") {
+            Some((low, high)) => {
+                let low = low.parse::<i64>().map_err(|_| invalid())?;
+                let high = high.parse::<i64>().map_err(|_| invalid())?;
+                let len = (high as i128 - low as i128 + 1).max(0);
+                if domain.len() as i128 + len > MAX_DOMAIN_SIZE as i128 {
+                    return Err(ParseModelError::DomainTooLarge(line, s.to_string()));
+                }
+                domain.extend(low..=high);
+            }
+            None => domain.push(part.parse().map_err(|_| invalid())?),
+        }
+    }
+    if domain.len() > MAX_DOMAIN_SIZE {
+        return Err(ParseModelError::DomainTooLarge(line, s.to_string()));
+    }
+    Ok(domain.into_iter().unique().collect())
+}
+
+/// 線形式を字句に分ける。記号の前後の空白は省略できる。
+fn tokenize(s: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut previous = None;
+    for c in s.chars() {
+        let kind = if c.is_whitespace() {
+            None
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            Some(0)
+        } else if "=!<>".contains(c) {
+            Some(1)
+        } else {
+            // + - * は 1 文字で 1 語
+            Some(2)
+        };
+        match (kind, tokens.last_mut()) {
+            (Some(k), Some(last)) if k != 2 && previous == kind => last.push(c),
+            (Some(_), _) => tokens.push(c.to_string()),
+            (None, _) => {}
+        }
+        previous = kind;
+    }
+    tokens
+}
+
+/// 線形式を係数と変数の組と定数項に分ける。同じ変数の項はまとめる。
+fn parse_expression(
+    tokens: &[String],
+    line: usize,
+) -> Result<(Vec<(i64, String)>, i64), ParseModelError> {
+    let invalid = |token: &str| ParseModelError::InvalidToken(line, token.to_string());
+    let mut terms: Vec<(i64, String)> = Vec::new();
+    let mut constant = 0i64;
+    let mut tokens = tokens.iter().map(String::as_str).peekable();
+    let mut sign = 1;
+    if tokens.peek() == Some(&"-") {
+        tokens.next();
+        sign = -1;
+    }
+    loop {
+        let token = tokens.next().ok_or(ParseModelError::InvalidLine(line))?;
+        let (coefficient, variable) = if is_identifier(token) {
+            (1, Some(token))
+        } else {
+            let number = token.parse::<i64>().map_err(|_| invalid(token))?;
+            if tokens.peek() == Some(&"*") {
+                tokens.next();
+                let variable = tokens.next().ok_or(ParseModelError::InvalidLine(line))?;
+                if !is_identifier(variable) {
+                    return Err(invalid(variable));
+                }
+                (number, Some(variable))
+            } else {
+                (number, None)
+            }
+        };
+        let overflow = || invalid(token);
+        let coefficient = coefficient.checked_mul(sign).ok_or_else(overflow)?;
+        match variable {
+            Some(variable) => match terms.iter_mut().find(|(_, v)| v == variable) {
+                Some((c, _)) => *c = c.checked_add(coefficient).ok_or_else(overflow)?,
+                None => terms.push((coefficient, variable.to_string())),
+            },
+            None => constant = constant.checked_add(coefficient).ok_or_else(overflow)?,
+        }
+        match tokens.next() {
+            Some("+") => sign = 1,
+            Some("-") => sign = -1,
+            Some(token) => return Err(invalid(token)),
+            None => return Ok((terms, constant)),
+        }
+    }
+}
+
+/// `左辺 関係 右辺` を `項の和 関係 定数` に移項する。
+fn parse_linear(s: &str, line: usize) -> Result<ModelConstraint, ParseModelError> {
+    let tokens = tokenize(s);
+    let (position, relation) = tokens
+        .iter()
+        .enumerate()
+        .find_map(|(i, token)| Relation::from_symbol(token).map(|r| (i, r)))
+        .ok_or(ParseModelError::InvalidLine(line))?;
+    let (mut terms, lhs_constant) = parse_expression(&tokens[..position], line)?;
+    let (rhs_terms, rhs_constant) = parse_expression(&tokens[position + 1..], line)?;
+    let overflow = || ParseModelError::InvalidToken(line, s.to_string());
+    for (coefficient, variable) in rhs_terms {
+        match terms.iter_mut().find(|(_, v)| *v == variable) {
+            Some((c, _)) => *c = c.checked_sub(coefficient).ok_or_else(overflow)?,
+            None => terms.push((coefficient.checked_neg().ok_or_else(overflow)?, variable)),
+        }
+    }
+    Ok(ModelConstraint::Linear {
+        terms,
+        relation,
+        constant: rhs_constant
+            .checked_sub(lhs_constant)
+            .ok_or_else(overflow)?,
+        bounds: Vec::new(),
+    })
+}
+
+impl FromStr for Model {
+    type Err = ParseModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut model = Model::default();
+        for (i, line) in s.lines().enumerate() {
+            let number = i + 1;
+            let line = line.split('#').next().unwrap().trim();
+            let invalid = |token: &str| ParseModelError::InvalidToken(number, token.to_string());
+            let (keyword, rest) = match line.split_once(char::is_whitespace) {
+                Some((keyword, rest)) => (keyword, rest.trim()),
+                None if line.is_empty() => continue,
+                None => return Err(ParseModelError::InvalidLine(number)),
+            };
+            match keyword {
+                "var" => {
+                    let words = rest.split_whitespace().collect::<Vec<_>>();
+                    let position = words
+                        .iter()
+                        .position(|w| *w == "in")
+                        .filter(|position| *position > 0)
+                        .ok_or(ParseModelError::InvalidLine(number))?;
+                    let domain = words[position + 1..].concat();
+                    let domain = parse_domain(&domain, number)?;
+                    for variable in &words[..position] {
+                        if !is_identifier(variable) {
+                            return Err(invalid(variable));
+                        }
+                        model.variables.push(variable.to_string());
+                        model.domains.insert(variable.to_string(), domain.clone());
+                    }
+                }
+                "alldifferent" => {
+                    let variables = rest.split_whitespace().collect::<Vec<_>>();
+                    if let Some(variable) = variables.iter().find(|v| !is_identifier(v)) {
+                        return Err(invalid(variable));
+                    }
+                    model.constraints.push(ModelConstraint::AllDifferent(
+                        variables.into_iter().map(String::from).collect(),
+                    ));
+                }
+                "constraint" => model.constraints.push(parse_linear(rest, number)?),
+                _ => return Err(invalid(keyword)),
+            }
+        }
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linear(terms: &[(i64, &str)], relation: Relation, constant: i64) -> ModelConstraint {
+        ModelConstraint::Linear {
+            terms: terms.iter().map(|(c, v)| (*c, v.to_string())).collect(),
+            relation,
+            constant,
+            bounds: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_linear() {
+        let model = Model::from_str(
+            "var x y in 1..3
+             constraint 2*x + 3 - y >= x+1 # 移項してまとめる
+             constraint x!=y",
+        )
+        .unwrap();
+        assert_eq!(
+            model.constraints(),
+            [
+                linear(&[(1, "x"), (-1, "y")], Relation::Ge, -2),
+                linear(&[(1, "x"), (-1, "y")], Relation::Ne, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_domain() {
+        assert_eq!(parse_domain("1..3,7", 1), Ok(vec![1, 2, 3, 7]));
+        assert_eq!(parse_domain("-1,-1", 1), Ok(vec![-1]));
+        assert_eq!(
+            parse_domain("1..x", 1),
+            Err(ParseModelError::InvalidToken(1, "1..x".to_string()))
+        );
+        assert_eq!(
+            Model::from_str("var x in 0..1000000000000"),
+            Err(ParseModelError::DomainTooLarge(
+                1,
+                "0..1000000000000".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_domain("-9223372036854775808..9223372036854775807", 1),
+            Err(ParseModelError::DomainTooLarge(
+                1,
+                "-9223372036854775808..9223372036854775807".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_constant_constraint() {
+        let model = Model::from_str(
+            "var x in 1..3
+constraint 1 = 2",
+        )
+        .unwrap();
+        assert_eq!(model.csp().unwrap().backtracking_search(), None);
+        let model = Model::from_str(
+            "var x in 1..3
+constraint x + 1 > x
+constraint 2 >= 2",
+        )
+        .unwrap();
+        assert_eq!(model.csp().unwrap().count_solutions(usize::MAX), 3);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            Model::from_str("var x in 1..3\nconstraint x + * 2 = 3"),
+            Err(ParseModelError::InvalidToken(2, "*".to_string()))
+        );
+        assert_eq!(
+            Model::from_str("var x 1..3"),
+            Err(ParseModelError::InvalidLine(1))
+        );
+        assert_eq!(
+            Model::from_str("let x in 1..3"),
+            Err(ParseModelError::InvalidToken(1, "let".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_unknown_variable() {
+        let model = Model::from_str("var x in 1..3\nalldifferent x y").unwrap();
+        assert_eq!(
+            model.csp().err(),
+            Some(CspError::UnknownVariable("y".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_map_coloring() {
+        let model = Model::from_str(include_str!("../../map_coloring.csp")).unwrap();
+        let solution = model.csp().unwrap().backtracking_search().unwrap();
+        for constraint in model.constraints() {
+            assert!(constraint.satisfied(&solution));
+        }
+    }
+
+    #[test]
+    fn test_send_more_money() {
+        let model = Model::from_str(include_str!("../../send_more_money.csp")).unwrap();
+        let solution = model.csp().unwrap().backtracking_search().unwrap();
+        let word = |letters: &str| {
+            letters
+                .chars()
+                .fold(0, |n, letter| n * 10 + solution[&letter.to_string()])
+        };
+        assert_eq!(word("SEND") + word("MORE"), word("MONEY"));
+        assert_eq!(word("MONEY"), 10652);
+    }
+}
@@ -10,6 +10,12 @@ pub struct Arena<T> {
     phantom: PhantomData<T>,
 }
 
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Arena<T> {
     pub fn new() -> Self {
         Self {
@@ -18,7 +24,7 @@ impl<T> Arena<T> {
         }
     }
 
-    pub fn add_node<'bump>(&'bump self, node: Node<'bump, T>) -> &'bump Node<T> {
+    pub fn add_node<'bump>(&'bump self, node: Node<'bump, T>) -> &'bump Node<'bump, T> {
         let node = self.bump.alloc(node);
         node
     }
@@ -36,7 +42,7 @@ impl<'bump, T> Node<'bump, T> {
     pub fn new(state: T, parent: Option<&'bump Node<'bump, T>>) -> Self {
         Self {
             state,
-            parent: parent,
+            parent,
             cost: 0.0,
             heuristic: 0.0,
         }
@@ -50,7 +56,7 @@ impl<'bump, T> Node<'bump, T> {
     ) -> Self {
         Self {
             state,
-            parent: parent,
+            parent,
             cost,
             heuristic,
         }
@@ -184,7 +190,7 @@ where
         }
         for child in successors(&node.state) {
             let new_cost = node.cost + 1.0;
-            if explored.get(&child).is_none() || explored[&child] > new_cost {
+            if !explored.contains_key(&child) || explored[&child] > new_cost {
                 explored.insert(child.clone(), new_cost);
                 let node =
                     Node::with_heuristic(child.clone(), Some(node), new_cost, heuristic(&child));
@@ -1,32 +1,144 @@
-use std::{cell::RefCell, rc::Rc};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use itertools::Itertools;
 use ordered_float::OrderedFloat;
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+pub mod checkpoint;
+pub mod continuous;
+pub mod genetic_programming;
+pub mod island;
+pub mod nsga2;
+pub mod operators;
+pub mod termination;
+
+use checkpoint::Checkpoint;
+use operators::{
+    ChromosomeCrossover, ChromosomeMutation, Crossover, Generational, Mutation, Replacement,
+    Selection, Tournament,
+};
+use termination::{Criterion, Progress, Termination};
 
 /// 染色体
 pub trait Chromosome: Sized + Clone {
     fn fitness(&self) -> f64;
-    fn random_instance() -> Self;
-    fn crossover(&self, other: &Self) -> (Self, Self);
-    fn mutate(&mut self);
+    fn random_instance<R: Rng + ?Sized>(rng: &mut R) -> Self;
+    fn crossover<R: Rng + ?Sized>(&self, other: &Self, rng: &mut R) -> (Self, Self);
+    fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R);
+}
+
+/// 適応度を覚えておく個体。交叉や突然変異で変わるまで適応度は計算し直さない。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Individual<C> {
+    pub chromosome: C,
+    #[serde(with = "checkpoint::optional_float")]
+    fitness: Option<f64>,
+}
+
+impl<C: Chromosome> Individual<C> {
+    fn new(chromosome: C) -> Self {
+        Self {
+            chromosome,
+            fitness: None,
+        }
+    }
+
+    pub fn fitness(&self) -> f64 {
+        self.fitness.unwrap_or_else(|| self.chromosome.fitness())
+    }
+
+    fn evaluate(&mut self) {
+        if self.fitness.is_none() {
+            self.fitness = Some(self.chromosome.fitness());
+        }
+    }
+}
+
+/// まだ評価していない個体の適応度を threads 本のスレッドで計算する。
+fn evaluate<C: Chromosome + Send>(population: &mut [Individual<C>], threads: usize) {
+    let chunk_size = population.len().div_ceil(threads.max(1)).max(1);
+    thread::scope(|s| {
+        for chunk in population.chunks_mut(chunk_size) {
+            s.spawn(|| chunk.iter_mut().for_each(Individual::evaluate));
+        }
+    });
+}
+
+/// checkpoint を書き出す関数
+type SaveCheckpoint<C> = fn(&Checkpoint<C>, &Path) -> std::io::Result<()>;
+
+/// 1 世代の統計
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub generation: u32,
+    #[serde(with = "checkpoint::float")]
+    pub best: f64,
+    #[serde(with = "checkpoint::float")]
+    pub mean: f64,
+    #[serde(with = "checkpoint::float")]
+    pub worst: f64,
+    /// 多様性の目安としての適応度の標準偏差
+    #[serde(with = "checkpoint::float")]
+    pub diversity: f64,
+    /// run を始めてからこの世代を評価し終えるまでの時間
+    pub elapsed: Duration,
 }
 
-pub enum SelectionType {
-    Roulette,
-    Tournament,
+impl GenerationStats {
+    fn new(generation: u32, fitness: &[f64], elapsed: Duration) -> Self {
+        let n = fitness.len() as f64;
+        let mean = fitness.iter().sum::<f64>() / n;
+        let variance = fitness.iter().map(|f| (f - mean).powi(2)).sum::<f64>() / n;
+        Self {
+            generation,
+            best: fitness.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            worst: fitness.iter().copied().fold(f64::INFINITY, f64::min),
+            diversity: variance.sqrt(),
+            elapsed,
+        }
+    }
 }
 
+/// 選択、交叉、突然変異、世代交代は operators の戦略に差し替えられる。
+/// 既定では個体数の半分の大きさのトーナメントで選び、Chromosome の交叉と突然変異を使い、
+/// 子ですべて入れ替える。
+/// 乱数は seed で固定できる。
+/// 世代交代の戦略は Individual<C> の列に対して呼ばれる。
 pub struct GeneticAlgorithm<C>
 where
     C: Chromosome,
 {
-    population: Vec<Rc<RefCell<C>>>,
+    population: Vec<Individual<C>>,
     threshold: f64,
     max_generations: u32,
     mutation_chance: f64,
     crossover_chance: f64,
-    selection_type: SelectionType,
+    selection: Box<dyn Selection + Send>,
+    crossover: Box<dyn Crossover<C> + Send>,
+    mutation: Box<dyn Mutation<C> + Send>,
+    replacement: Box<dyn Replacement<Individual<C>> + Send>,
+    /// threshold と max_generations のほかの止める条件
+    criteria: Vec<Box<dyn Criterion + Send>>,
+    /// 適応度の評価。既定では threads に関わらず 1 つのスレッドで順に評価する。
+    evaluate: fn(&mut [Individual<C>], usize),
+    threads: usize,
+    history: Vec<GenerationStats>,
+    /// 途中まで進めた run の状態
+    generation: u32,
+    best: Option<(f64, C)>,
+    evaluations: usize,
+    start: Instant,
+    /// start より前に進めていた時間。resume したときだけ 0 でない。
+    elapsed_before: Duration,
+    /// resume で状態を戻したので、次の run を最初の世代からやり直さない
+    resumed: bool,
+    /// (世代の間隔, 書き出し先, 書き出す関数)
+    checkpoint: Option<(u32, PathBuf, SaveCheckpoint<C>)>,
+    rng: ChaCha12Rng,
 }
 
 impl<C> GeneticAlgorithm<C>
@@ -39,116 +151,512 @@ where
         max_generations: u32,
         mutation_chance: f64,
         crossover_chance: f64,
-        selection_type: SelectionType,
     ) -> Self {
-        let population = initial_population
-            .into_iter()
-            .map(|c| Rc::new(RefCell::new(c)))
-            .collect();
+        let tournament_size = initial_population.len() / 2;
         Self {
-            population,
+            population: initial_population
+                .into_iter()
+                .map(Individual::new)
+                .collect(),
             threshold,
             max_generations,
             mutation_chance,
             crossover_chance,
-            selection_type,
+            selection: Box::new(Tournament::new(tournament_size)),
+            crossover: Box::new(ChromosomeCrossover),
+            mutation: Box::new(ChromosomeMutation),
+            replacement: Box::new(Generational),
+            criteria: Vec::new(),
+            evaluate: |population, _| population.iter_mut().for_each(Individual::evaluate),
+            threads: 1,
+            history: Vec::new(),
+            generation: 0,
+            best: None,
+            evaluations: 0,
+            start: Instant::now(),
+            elapsed_before: Duration::ZERO,
+            resumed: false,
+            checkpoint: None,
+            rng: ChaCha12Rng::from_entropy(),
         }
     }
 
-    /// 確率分布のルーレットで 2 つの親を選ぶ。
-    /// 注記：負の適応度ではうまくいかない
-    fn pick_roulette(&self, wheel: &[f64]) -> (Rc<RefCell<C>>, Rc<RefCell<C>>) {
-        let mut rng = thread_rng();
-        let choices = self.population.iter().zip(wheel.iter()).collect::<Vec<_>>();
-        let picked = choices
-            .choose_multiple_weighted(&mut rng, 2, |x| *x.1)
-            .unwrap();
-        picked.map(|x| x.0.clone()).next_tuple().unwrap()
-    }
-
-    /// num 個の無作為抽出した個体から最良の 2 つを選ぶ
-    fn pick_tournament(&self, num_participants: usize) -> (Rc<RefCell<C>>, Rc<RefCell<C>>) {
-        let mut rng = thread_rng();
-        let picked = self.population.choose_multiple(&mut rng, num_participants);
-        let sorted = picked.sorted_by_key(|x| OrderedFloat(-x.borrow().fitness()));
-        sorted.cloned().next_tuple().unwrap()
-    }
-
-    fn reproduce_and_replace(&mut self) {
-        let mut rng = thread_rng();
-        let mut new_population = Vec::new();
-        while new_population.len() < self.population.len() {
-            // 両親を選ぶ
-            let (parent1, parent2) = match self.selection_type {
-                SelectionType::Roulette => {
-                    let wheel = self
-                        .population
-                        .iter()
-                        .map(|x| x.borrow().fitness())
-                        .collect::<Vec<_>>();
-                    self.pick_roulette(&wheel)
-                }
-                SelectionType::Tournament => self.pick_tournament(self.population.len() / 2),
-            };
-            // 両親を確立的に交叉させる
-            if rng.gen_bool(self.crossover_chance) {
-                let (child1, child2) = parent1.borrow().crossover(&parent2.borrow());
-                new_population.push(Rc::new(RefCell::new(child1)));
-                new_population.push(Rc::new(RefCell::new(child2)));
-            } else {
-                new_population.push(parent1.clone());
-                new_population.push(parent2.clone());
-            }
+    /// checkpoint の集団、世代、最良の個体、乱数の状態から続きを進める。
+    /// 戦略は保存されないので、new のあとと同じように設定し直す。
+    pub fn resume(
+        checkpoint: Checkpoint<C>,
+        threshold: f64,
+        max_generations: u32,
+        mutation_chance: f64,
+        crossover_chance: f64,
+    ) -> Self {
+        let tournament_size = checkpoint.population.len() / 2;
+        Self {
+            population: checkpoint.population,
+            selection: Box::new(Tournament::new(tournament_size)),
+            history: checkpoint.history,
+            generation: checkpoint.generation,
+            best: checkpoint.best,
+            evaluations: checkpoint.evaluations,
+            elapsed_before: checkpoint.elapsed,
+            resumed: true,
+            rng: checkpoint.rng,
+            ..Self::new(
+                Vec::new(),
+                threshold,
+                max_generations,
+                mutation_chance,
+                crossover_chance,
+            )
         }
-        // もし奇数だったら、1つ余分になるので、mutそれを削除する
-        if new_population.len() < self.population.len() {
-            new_population.pop();
+    }
+
+    /// 今の状態の写し
+    pub fn checkpoint(&self) -> Checkpoint<C> {
+        Checkpoint {
+            population: self.population.clone(),
+            generation: self.generation,
+            best: self.best.clone(),
+            evaluations: self.evaluations,
+            history: self.history.clone(),
+            elapsed: self.elapsed(),
+            rng: self.rng.clone(),
         }
-        self.population = new_population;
     }
 
-    fn mutate(&mut self) {
-        let mut rng = thread_rng();
-        for individual in self.population.iter() {
+    /// 同じ seed と初期集団なら同じ結果になる。
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn selection(mut self, selection: impl Selection + Send + 'static) -> Self {
+        self.selection = Box::new(selection);
+        self
+    }
+
+    pub fn crossover(mut self, crossover: impl Crossover<C> + Send + 'static) -> Self {
+        self.crossover = Box::new(crossover);
+        self
+    }
+
+    pub fn mutation(mut self, mutation: impl Mutation<C> + Send + 'static) -> Self {
+        self.mutation = Box::new(mutation);
+        self
+    }
+
+    pub fn replacement(
+        mut self,
+        replacement: impl Replacement<Individual<C>> + Send + 'static,
+    ) -> Self {
+        self.replacement = Box::new(replacement);
+        self
+    }
+
+    /// 止める条件を加える。どれか 1 つでも満たせば run を終える。
+    pub fn stop_when(mut self, criterion: impl Criterion + Send + 'static) -> Self {
+        self.criteria.push(Box::new(criterion));
+        self
+    }
+
+    /// 親を選んで子を作り、確率的に交叉と突然変異をさせてから世代を交代する。
+    fn reproduce_and_replace(&mut self, fitness: &[f64]) {
+        let rng = &mut self.rng;
+        let size = self.replacement.offspring_count(self.population.len());
+        let parents = self.selection.select(fitness, size + size % 2, rng);
+        let mut offspring = Vec::new();
+        for pair in parents.chunks(2) {
+            let (parent1, parent2) = (&self.population[pair[0]], &self.population[pair[1]]);
+            let (child1, child2) = if rng.gen_bool(self.crossover_chance) {
+                let (child1, child2) =
+                    self.crossover
+                        .crossover(&parent1.chromosome, &parent2.chromosome, rng);
+                (Individual::new(child1), Individual::new(child2))
+            } else {
+                (parent1.clone(), parent2.clone())
+            };
+            offspring.push(child1);
+            offspring.push(child2);
+        }
+        // もし奇数だったら、1つ余分になるので、それを削除する
+        offspring.truncate(size);
+        for child in offspring.iter_mut() {
             if rng.gen_bool(self.mutation_chance) {
-                individual.as_ref().borrow_mut().mutate();
+                self.mutation.mutate(&mut child.chromosome, rng);
+                child.fitness = None;
             }
         }
+        let population = std::mem::take(&mut self.population);
+        self.population = self.replacement.replace(population, fitness, offspring);
+    }
+
+    /// 直前の run の世代ごとの統計
+    pub fn history(&self) -> &[GenerationStats] {
+        &self.history
+    }
+
+    /// 最良の個体と、run を終えた理由を返す。
+    pub fn run(&mut self) -> (C, Termination) {
+        self.run_with(|_| {})
+    }
+
+    /// 世代を評価するたびにその統計を on_generation に渡す。
+    pub fn run_with<F>(&mut self, mut on_generation: F) -> (C, Termination)
+    where
+        F: FnMut(&GenerationStats),
+    {
+        if std::mem::take(&mut self.resumed) {
+            self.start = Instant::now();
+        } else {
+            self.reset();
+        }
+        loop {
+            if let Some(termination) = self.step(&mut on_generation) {
+                tracing::info!("Generation {} {}", self.generation, termination);
+                return (self.best().unwrap().clone(), termination);
+            }
+        }
+    }
+
+    /// run を最初の世代からやり直せるようにする。集団はそのまま引き継ぐ。
+    fn reset(&mut self) {
+        self.start = Instant::now();
+        self.elapsed_before = Duration::ZERO;
+        self.history.clear();
+        self.generation = 0;
+        self.best = None;
+        self.evaluations = 0;
+    }
+
+    /// run を始めてからの時間。resume する前の分も含む。
+    fn elapsed(&self) -> Duration {
+        self.elapsed_before + self.start.elapsed()
+    }
+
+    /// これまでの最良の個体
+    fn best(&self) -> Option<&C> {
+        self.best.as_ref().map(|(_, best)| best)
+    }
+
+    /// まだ評価していない個体の適応度を計算し、その数を数える。
+    fn evaluate_population(&mut self) {
+        self.evaluations += self
+            .population
+            .iter()
+            .filter(|x| x.fitness.is_none())
+            .count();
+        (self.evaluate)(&mut self.population, self.threads);
+    }
+
+    /// 止める条件を threshold、max_generations、加えた順に調べる。
+    fn termination(&self) -> Option<Termination> {
+        if self.best.as_ref().unwrap().0 >= self.threshold {
+            return Some(Termination::Threshold);
+        }
+        // resume で max_generations を checkpoint の世代より小さくしても止まるように >= で比べる
+        if self.generation >= self.max_generations {
+            return Some(Termination::MaxGenerations);
+        }
+        let progress = Progress {
+            history: &self.history,
+            evaluations: self.evaluations,
+        };
+        self.criteria
+            .iter()
+            .find_map(|criterion| criterion.check(&progress))
     }
 
-    pub fn run(&mut self) -> C {
-        let mut best = self
+    /// 今の世代を評価し、終了条件を満たしていればその理由を返す。
+    /// 満たしていなければ次の世代を作る。
+    fn step(&mut self, on_generation: &mut dyn FnMut(&GenerationStats)) -> Option<Termination> {
+        self.evaluate_population();
+        let fitness = self
             .population
             .iter()
-            .max_by_key(|x| OrderedFloat(x.borrow().fitness()))
-            .unwrap()
-            .clone();
-        for generation in 0..self.max_generations {
-            if best.borrow().fitness() >= self.threshold {
-                break;
+            .map(Individual::fitness)
+            .collect::<Vec<_>>();
+        let stats = GenerationStats::new(self.generation, &fitness, self.elapsed());
+        tracing::info!(
+            "Generation {} Best {} Avg {}",
+            self.generation,
+            stats.best,
+            stats.mean
+        );
+        on_generation(&stats);
+        self.history.push(stats);
+        let highest = (0..fitness.len())
+            .max_by_key(|i| OrderedFloat(fitness[*i]))
+            .unwrap();
+        if self
+            .best
+            .as_ref()
+            .is_none_or(|(f, _)| fitness[highest] > *f)
+        {
+            self.best = Some((
+                fitness[highest],
+                self.population[highest].chromosome.clone(),
+            ));
+        }
+        if let Some(termination) = self.termination() {
+            return Some(termination);
+        }
+        self.reproduce_and_replace(&fitness);
+        self.generation += 1;
+        if let Some((interval, path, save)) = &self.checkpoint {
+            if self.generation.is_multiple_of(*interval) {
+                if let Err(e) = save(&self.checkpoint(), path) {
+                    tracing::warn!("Failed to save checkpoint to {}: {}", path.display(), e);
+                }
             }
-            tracing::info!(
-                "Generation {} Best {} Avg {}",
-                generation,
-                best.borrow().fitness(),
-                self.population
-                    .iter()
-                    .map(|x| x.borrow().fitness())
-                    .sum::<f64>()
-                    / self.population.len() as f64
-            );
-            self.reproduce_and_replace();
-            self.mutate();
-            let highest = self
-                .population
-                .iter()
-                .max_by_key(|x| OrderedFloat(x.borrow().fitness()))
-                .unwrap();
-            if highest.borrow().fitness() > best.borrow().fitness() {
-                best = highest.clone();
+        }
+        None
+    }
+
+    /// 適応度の高い count 個の写し
+    fn emigrants(&mut self, count: usize) -> Vec<Individual<C>> {
+        self.evaluate_population();
+        let mut population = self.population.clone();
+        population.sort_by_key(|x| std::cmp::Reverse(OrderedFloat(x.fitness())));
+        population.truncate(count);
+        population
+    }
+
+    /// 移ってきた個体で適応度の低いものから置き換える。
+    fn immigrate(&mut self, immigrants: Vec<Individual<C>>) {
+        self.evaluate_population();
+        self.population
+            .sort_by_key(|x| std::cmp::Reverse(OrderedFloat(x.fitness())));
+        let size = self.population.len();
+        self.population
+            .truncate(size.saturating_sub(immigrants.len()));
+        self.population.extend(immigrants.into_iter().take(size));
+    }
+}
+
+impl<C> GeneticAlgorithm<C>
+where
+    C: Chromosome + Send,
+{
+    /// 適応度を threads 本のスレッドで並列に評価する。
+    /// 並べるのは評価だけなので、seed が同じなら結果は 1 スレッドのときと変わらない。
+    pub fn parallel(mut self, threads: usize) -> Self {
+        self.evaluate = evaluate;
+        self.threads = threads;
+        self
+    }
+}
+
+impl<C> GeneticAlgorithm<C>
+where
+    C: Chromosome + Serialize,
+{
+    /// interval 世代ごとに path へ checkpoint を書き出す。
+    /// 書き出しに失敗しても run は止めずに警告を出す。
+    pub fn checkpoint_every(mut self, interval: u32, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint = Some((interval.max(1), path.into(), Checkpoint::save));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rand::rngs::StdRng;
+
+    use super::operators::Elitism;
+    use super::*;
+
+    /// 1 の数を数える
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Ones(Vec<bool>);
+
+    impl Chromosome for Ones {
+        fn fitness(&self) -> f64 {
+            self.0.iter().filter(|b| **b).count() as f64
+        }
+
+        fn random_instance<R: Rng + ?Sized>(rng: &mut R) -> Self {
+            Self((0..16).map(|_| rng.gen_bool(0.5)).collect())
+        }
+
+        fn crossover<R: Rng + ?Sized>(&self, other: &Self, _rng: &mut R) -> (Self, Self) {
+            let (mut child1, mut child2) = (self.clone(), other.clone());
+            child1.0[8..].copy_from_slice(&other.0[8..]);
+            child2.0[8..].copy_from_slice(&self.0[8..]);
+            (child1, child2)
+        }
+
+        fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+            let i = rng.gen_range(0..self.0.len());
+            self.0[i] = !self.0[i];
+        }
+    }
+
+    #[test]
+    fn test_elitism_keeps_best() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let population = (0..10).map(|_| Ones::random_instance(&mut rng)).collect();
+        let mut ga =
+            GeneticAlgorithm::new(population, 17.0, 30, 0.5, 0.7).replacement(Elitism::new(1));
+        let mut streamed = 0;
+        let (_, termination) = ga.run_with(|_| streamed += 1);
+        assert_eq!(termination, Termination::MaxGenerations);
+        let history = ga.history();
+        assert_eq!(history.len(), 31);
+        assert_eq!(streamed, 31);
+        assert!(history.windows(2).all(|w| w[0].best <= w[1].best));
+        assert!(history
+            .iter()
+            .all(|s| s.worst <= s.mean && s.mean <= s.best));
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        let run = |threads| {
+            let mut rng = StdRng::seed_from_u64(1);
+            let population = (0..20).map(|_| Ones::random_instance(&mut rng)).collect();
+            let mut ga = GeneticAlgorithm::new(population, 17.0, 20, 0.5, 0.7).seed(2);
+            if threads > 1 {
+                ga = ga.parallel(threads);
             }
+            let best = ga.run();
+            let bests = ga.history().iter().map(|s| s.best).collect::<Vec<_>>();
+            (best.0, bests)
+        };
+        assert_eq!(run(1), run(4));
+    }
+
+    #[test]
+    fn test_stop_when() {
+        let ga = || {
+            let mut rng = StdRng::seed_from_u64(5);
+            let population = (0..10).map(|_| Ones::random_instance(&mut rng)).collect();
+            GeneticAlgorithm::new(population, 100.0, 1000, 0.5, 0.7).seed(6)
+        };
+        let mut stagnating = ga().stop_when(termination::Stagnation::new(20));
+        let (best, termination) = stagnating.run();
+        assert_eq!(termination, Termination::Stagnation);
+        let history = stagnating.history();
+        assert!(history[history.len() - 20..]
+            .iter()
+            .all(|s| s.best <= best.fitness()));
+
+        let mut budgeted = ga()
+            .stop_when(termination::EvaluationBudget(50))
+            .stop_when(termination::Stagnation::new(20));
+        assert_eq!(budgeted.run().1, Termination::EvaluationBudget);
+        assert!(budgeted.evaluations >= 50 && budgeted.evaluations < 60);
+    }
+
+    static EVALUATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    /// 適応度を計算した回数を数える
+    #[derive(Debug, Clone)]
+    struct Counted(Ones);
+
+    impl Chromosome for Counted {
+        fn fitness(&self) -> f64 {
+            EVALUATIONS.fetch_add(1, Ordering::SeqCst);
+            self.0.fitness()
         }
-        let cloned = best.borrow().clone();
-        cloned
+
+        fn random_instance<R: Rng + ?Sized>(rng: &mut R) -> Self {
+            Self(Ones::random_instance(rng))
+        }
+
+        fn crossover<R: Rng + ?Sized>(&self, other: &Self, rng: &mut R) -> (Self, Self) {
+            let (child1, child2) = self.0.crossover(&other.0, rng);
+            (Self(child1), Self(child2))
+        }
+
+        fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+            self.0.mutate(rng);
+        }
+    }
+
+    #[test]
+    fn test_fitness_cached() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let population = (0..10)
+            .map(|_| Counted::random_instance(&mut rng))
+            .collect();
+        // 交叉も突然変異もしなければ、子は親の適応度をそのまま使う
+        let mut ga = GeneticAlgorithm::new(population, 100.0, 5, 0.0, 0.0).parallel(2);
+        ga.run();
+        assert_eq!(EVALUATIONS.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let path = std::env::temp_dir().join("test_resume_from_checkpoint.json");
+        let ga = |max_generations| {
+            let mut rng = StdRng::seed_from_u64(3);
+            let population = (0..10).map(|_| Ones::random_instance(&mut rng)).collect();
+            GeneticAlgorithm::new(population, 100.0, max_generations, 0.5, 0.7).seed(4)
+        };
+        let mut whole = ga(15).checkpoint_every(10, &path);
+        let best = whole.run();
+        // 10 世代目の checkpoint から続けても、通しで進めたときと同じになる
+        let checkpoint = Checkpoint::<Ones>::load(&path).unwrap();
+        assert_eq!(checkpoint.generation, 10);
+        let mut resumed = GeneticAlgorithm::resume(checkpoint, 100.0, 15, 0.5, 0.7);
+        assert_eq!(resumed.run(), best);
+        assert_eq!(
+            resumed.history().iter().map(|s| s.best).collect::<Vec<_>>(),
+            whole.history().iter().map(|s| s.best).collect::<Vec<_>>()
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_non_finite() {
+        let path = std::env::temp_dir().join("test_checkpoint_non_finite.json");
+        let mut rng = StdRng::seed_from_u64(3);
+        let population = (0..10).map(|_| Ones::random_instance(&mut rng)).collect();
+        let mut ga = GeneticAlgorithm::new(population, 100.0, 3, 0.5, 0.7).seed(4);
+        ga.run();
+        let mut checkpoint = ga.checkpoint();
+        checkpoint.population[0].fitness = Some(f64::NEG_INFINITY);
+        checkpoint.best.as_mut().unwrap().0 = f64::INFINITY;
+        checkpoint.history[0].worst = f64::NEG_INFINITY;
+        checkpoint.history[0].mean = f64::NAN;
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::<Ones>::load(&path).unwrap();
+        assert_eq!(loaded.population[0].fitness, Some(f64::NEG_INFINITY));
+        assert_eq!(loaded.best.unwrap().0, f64::INFINITY);
+        assert_eq!(loaded.history[0].worst, f64::NEG_INFINITY);
+        assert!(loaded.history[0].mean.is_nan());
+        assert_eq!(loaded.history[1..], checkpoint.history[1..]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_resume_below_checkpoint_generation() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let population = (0..10).map(|_| Ones::random_instance(&mut rng)).collect();
+        let mut ga = GeneticAlgorithm::new(population, 100.0, 10, 0.5, 0.7).seed(4);
+        ga.run();
+        let checkpoint = ga.checkpoint();
+        assert_eq!(checkpoint.generation, 10);
+        let (_, termination) = GeneticAlgorithm::resume(checkpoint, 100.0, 5, 0.5, 0.7).run();
+        assert_eq!(termination, Termination::MaxGenerations);
+    }
+
+    #[test]
+    fn test_resume_keeps_elapsed() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let population = (0..10).map(|_| Ones::random_instance(&mut rng)).collect();
+        let mut ga = GeneticAlgorithm::new(population, 100.0, 10, 0.5, 0.7).seed(4);
+        ga.run();
+        let mut checkpoint = ga.checkpoint();
+        assert!(checkpoint.elapsed >= ga.history().last().unwrap().elapsed);
+        // 1 時間進めたことにして続けると、すぐに時間の上限で止まる
+        checkpoint.elapsed = Duration::from_secs(3600);
+        let mut resumed = GeneticAlgorithm::resume(checkpoint, 100.0, 100, 0.5, 0.7)
+            .stop_when(termination::TimeLimit(Duration::from_secs(60)));
+        let (_, termination) = resumed.run();
+        assert_eq!(termination, Termination::TimeLimit);
+        assert!(resumed.history().last().unwrap().elapsed >= Duration::from_secs(3600));
     }
 }
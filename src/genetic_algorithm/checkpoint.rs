@@ -0,0 +1,133 @@
+//! GeneticAlgorithm の途中の状態を保存し、あとで続きから進める。
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use rand_chacha::ChaCha12Rng;
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{GenerationStats, Individual};
+
+/// 集団、世代、最良の個体、それまでの統計、経過時間と乱数の状態。
+/// 染色体が Serialize と Deserialize を実装していれば JSON に読み書きできる。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint<C> {
+    pub population: Vec<Individual<C>>,
+    pub generation: u32,
+    #[serde(with = "best")]
+    pub best: Option<(f64, C)>,
+    /// run を始めてから適応度を計算した回数
+    pub evaluations: usize,
+    pub history: Vec<GenerationStats>,
+    /// run を始めてからの時間
+    pub elapsed: Duration,
+    pub rng: ChaCha12Rng,
+}
+
+impl<C> Checkpoint<C>
+where
+    C: Serialize,
+{
+    /// 書きかけのファイルが残らないよう、一時ファイルに書いてから置き換える。
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, serde_json::to_vec(self)?)?;
+        fs::rename(temporary, path)
+    }
+}
+
+impl<C> Checkpoint<C>
+where
+    C: DeserializeOwned,
+{
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+}
+
+/// serde_json は無限大と NaN を null と書いて読み戻せないので、有限でない値は
+/// "inf"、"-inf"、"NaN" の文字列で書く。
+#[derive(Debug, Clone, Copy)]
+struct Float(f64);
+
+impl Serialize for Float {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.0.is_finite() {
+            serializer.serialize_f64(self.0)
+        } else {
+            serializer.serialize_str(&self.0.to_string())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Float {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Number(f64),
+            Text(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Number(x) => Ok(Float(x)),
+            Repr::Text(text) => text
+                .parse()
+                .map(Float)
+                .map_err(|_| D::Error::custom(format!("invalid float {:?}", text))),
+        }
+    }
+}
+
+/// f64 のフィールドにつける `#[serde(with = "checkpoint::float")]`
+pub(super) mod float {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(x: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        Float(*x).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Ok(Float::deserialize(deserializer)?.0)
+    }
+}
+
+/// `Option<f64>` のフィールドにつける `#[serde(with = "checkpoint::optional_float")]`
+pub(super) mod optional_float {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(x: &Option<f64>, serializer: S) -> Result<S::Ok, S::Error> {
+        x.map(Float).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<f64>, D::Error> {
+        Ok(Option::<Float>::deserialize(deserializer)?.map(|x| x.0))
+    }
+}
+
+/// 最良の適応度と個体
+mod best {
+    use super::*;
+
+    pub fn serialize<S, C>(best: &Option<(f64, C)>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        C: Serialize,
+    {
+        best.as_ref()
+            .map(|(fitness, best)| (Float(*fitness), best))
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D, C>(deserializer: D) -> Result<Option<(f64, C)>, D::Error>
+    where
+        D: Deserializer<'de>,
+        C: Deserialize<'de>,
+    {
+        Ok(Option::<(Float, C)>::deserialize(deserializer)?
+            .map(|(fitness, best)| (fitness.0, best)))
+    }
+}
@@ -0,0 +1,171 @@
+//! 実数値の最適化。箱型の制約の中で Vec<f64> を動かし、目的関数を最小にする。
+use rand::Rng;
+
+pub mod cma_es;
+pub mod differential_evolution;
+pub mod evolution_strategy;
+
+/// 変数ごとの下限と上限
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bounds {
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+}
+
+impl Bounds {
+    pub fn new(lower: Vec<f64>, upper: Vec<f64>) -> Self {
+        assert_eq!(lower.len(), upper.len());
+        assert!(lower.iter().zip(&upper).all(|(l, u)| l <= u));
+        Self { lower, upper }
+    }
+
+    /// どの変数も lower 以上 upper 以下
+    pub fn uniform(dimension: usize, lower: f64, upper: f64) -> Self {
+        Self::new(vec![lower; dimension], vec![upper; dimension])
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.lower.len()
+    }
+
+    pub fn lower(&self) -> &[f64] {
+        &self.lower
+    }
+
+    pub fn upper(&self) -> &[f64] {
+        &self.upper
+    }
+
+    /// i 番目の変数の幅
+    pub fn width(&self, i: usize) -> f64 {
+        self.upper[i] - self.lower[i]
+    }
+
+    /// はみ出した変数を境界に戻す。
+    pub fn clamp(&self, x: &mut [f64]) {
+        for ((x, lower), upper) in x.iter_mut().zip(&self.lower).zip(&self.upper) {
+            *x = x.clamp(*lower, *upper);
+        }
+    }
+
+    /// 箱の中から一様に選んだ点
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<f64> {
+        self.lower
+            .iter()
+            .zip(&self.upper)
+            .map(|(lower, upper)| rng.gen_range(*lower..=*upper))
+            .collect()
+    }
+}
+
+/// 見つけた最良の点と、目的関数を呼んだ回数
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub x: Vec<f64>,
+    pub value: f64,
+    pub evaluations: usize,
+}
+
+/// 目的関数を最小にする。
+pub trait Optimizer {
+    fn minimize(&mut self, objective: &dyn Fn(&[f64]) -> f64) -> Solution;
+}
+
+/// 目的関数を呼んだ回数と、それまでで最良の点を覚えておく。
+/// NaN はどの数よりも悪いとみなす。
+struct Tracker<'a> {
+    objective: &'a dyn Fn(&[f64]) -> f64,
+    best: Option<(Vec<f64>, f64)>,
+    evaluations: usize,
+}
+
+impl<'a> Tracker<'a> {
+    fn new(objective: &'a dyn Fn(&[f64]) -> f64) -> Self {
+        Self {
+            objective,
+            best: None,
+            evaluations: 0,
+        }
+    }
+
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        let value = (self.objective)(x);
+        self.evaluations += 1;
+        let rank = |value: f64| if value.is_nan() { f64::INFINITY } else { value };
+        if self
+            .best
+            .as_ref()
+            .is_none_or(|(_, best)| rank(value).total_cmp(&rank(*best)).is_lt())
+        {
+            self.best = Some((x.to_vec(), value));
+        }
+        value
+    }
+
+    fn solution(self) -> Solution {
+        let (x, value) = self.best.unwrap();
+        Solution {
+            x,
+            value,
+            evaluations: self.evaluations,
+        }
+    }
+}
+
+/// 原点で最小値 0 をとる。
+pub fn sphere(x: &[f64]) -> f64 {
+    x.iter().map(|x| x * x).sum()
+}
+
+/// (1, ..., 1) で最小値 0 をとる。谷が細く曲がっている。
+pub fn rosenbrock(x: &[f64]) -> f64 {
+    x.windows(2)
+        .map(|w| 100.0 * (w[1] - w[0] * w[0]).powi(2) + (1.0 - w[0]).powi(2))
+        .sum()
+}
+
+/// 原点で最小値 0 をとる。局所解が格子状に並ぶ。
+pub fn rastrigin(x: &[f64]) -> f64 {
+    10.0 * x.len() as f64
+        + x.iter()
+            .map(|x| x * x - 10.0 * (2.0 * std::f64::consts::PI * x).cos())
+            .sum::<f64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_benchmarks() {
+        assert_eq!(sphere(&[0.0; 4]), 0.0);
+        assert_eq!(rosenbrock(&[1.0; 4]), 0.0);
+        assert!(rastrigin(&[0.0; 4]).abs() < 1e-12);
+        assert!(rastrigin(&[1.0, 0.0]) > rastrigin(&[0.0, 0.0]));
+    }
+
+    #[test]
+    fn test_bounds() {
+        let bounds = Bounds::new(vec![-1.0, 0.0], vec![1.0, 5.0]);
+        let mut x = vec![2.0, -1.0];
+        bounds.clamp(&mut x);
+        assert_eq!(x, vec![1.0, 0.0]);
+        let mut rng = StdRng::seed_from_u64(0);
+        let x = bounds.sample(&mut rng);
+        assert!((-1.0..=1.0).contains(&x[0]) && (0.0..=5.0).contains(&x[1]));
+    }
+
+    #[test]
+    fn test_tracker_nan() {
+        let objective = |x: &[f64]| if x[0] < 0.0 { f64::NAN } else { x[0] };
+        let mut tracker = Tracker::new(&objective);
+        tracker.evaluate(&[-1.0]);
+        tracker.evaluate(&[2.0]);
+        tracker.evaluate(&[-2.0]);
+        tracker.evaluate(&[1.0]);
+        let solution = tracker.solution();
+        assert_eq!((solution.x, solution.value), (vec![1.0], 1.0));
+        assert_eq!(solution.evaluations, 4);
+    }
+}
@@ -0,0 +1,221 @@
+//! 共分散行列適応進化戦略 (CMA-ES)
+use ndarray::{Array1, Array2, Axis};
+use ndarray_rand::rand_distr::StandardNormal;
+use ordered_float::OrderedFloat;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use super::{Bounds, Optimizer, Solution, Tracker};
+
+/// 多変量正規分布から λ 個を取り出し、良い μ 個に合わせて平均、歩幅、共分散行列を更新する。
+/// 箱からはみ出した点は境界に戻し、戻した点で分布を更新する。
+pub struct CmaEs {
+    bounds: Bounds,
+    lambda: usize,
+    generations: u32,
+    rng: ChaCha12Rng,
+}
+
+impl CmaEs {
+    /// 集団の大きさは既定で 4 + 3 ln(次元)。
+    pub fn new(bounds: Bounds, generations: u32) -> Self {
+        let lambda = 4 + (3.0 * (bounds.dimension() as f64).ln()).floor() as usize;
+        Self {
+            bounds,
+            lambda,
+            generations,
+            rng: ChaCha12Rng::from_entropy(),
+        }
+    }
+
+    /// 集団を大きくすると、局所解の多い関数でも大域的な構造をつかみやすくなる。
+    pub fn population(mut self, lambda: usize) -> Self {
+        self.lambda = lambda.max(2);
+        self
+    }
+
+    /// 同じ seed なら同じ結果になる。
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self
+    }
+}
+
+impl Optimizer for CmaEs {
+    fn minimize(&mut self, objective: &dyn Fn(&[f64]) -> f64) -> Solution {
+        let mut tracker = Tracker::new(objective);
+        let n = self.bounds.dimension();
+        let nf = n as f64;
+        let lambda = self.lambda;
+        let mu = lambda / 2;
+        let weights =
+            Array1::from_iter((0..mu).map(|i| (mu as f64 + 0.5).ln() - (i as f64 + 1.0).ln()));
+        let weights = &weights / weights.sum();
+        let mueff = 1.0 / weights.mapv(|w| w * w).sum();
+        let cc = (4.0 + mueff / nf) / (nf + 4.0 + 2.0 * mueff / nf);
+        let cs = (mueff + 2.0) / (nf + mueff + 5.0);
+        let c1 = 2.0 / ((nf + 1.3).powi(2) + mueff);
+        let cmu = (1.0 - c1).min(2.0 * (mueff - 2.0 + 1.0 / mueff) / ((nf + 2.0).powi(2) + mueff));
+        let damps = 1.0 + 2.0 * (((mueff - 1.0) / (nf + 1.0)).sqrt() - 1.0).max(0.0) + cs;
+        let chi_n = nf.sqrt() * (1.0 - 1.0 / (4.0 * nf) + 1.0 / (21.0 * nf * nf));
+
+        let mut mean = Array1::from(self.bounds.sample(&mut self.rng));
+        let mut sigma = 0.3 * (0..n).map(|i| self.bounds.width(i)).sum::<f64>() / nf;
+        let mut c = Array2::<f64>::eye(n);
+        let mut b = Array2::<f64>::eye(n);
+        let mut d = Array1::<f64>::ones(n);
+        let mut pc = Array1::<f64>::zeros(n);
+        let mut ps = Array1::<f64>::zeros(n);
+        // 世代数が 0 でも最初の平均を答えにできるように評価しておく
+        tracker.evaluate(&mean.to_vec());
+
+        for generation in 0..self.generations {
+            // 点を取り出して評価し、良い順に並べる
+            let mut samples = (0..lambda)
+                .map(|_| {
+                    let z = Array1::from_iter(
+                        (0..n).map(|_| self.rng.sample::<f64, _>(StandardNormal)),
+                    );
+                    let mut x = (&mean + &(sigma * b.dot(&(&d * &z)))).to_vec();
+                    self.bounds.clamp(&mut x);
+                    let value = tracker.evaluate(&x);
+                    let y = (Array1::from(x) - &mean) / sigma;
+                    (y, value)
+                })
+                .collect::<Vec<_>>();
+            samples.sort_by_key(|(_, value)| OrderedFloat(*value));
+            let best = &samples[..mu];
+            let y_w = best
+                .iter()
+                .zip(&weights)
+                .fold(Array1::zeros(n), |acc, ((y, _), w)| acc + *w * y);
+            mean = mean + sigma * &y_w;
+
+            // 進化の経路を更新する
+            let c_inv_sqrt_y = b.dot(&(b.t().dot(&y_w) / &d));
+            ps = (1.0 - cs) * &ps + (cs * (2.0 - cs) * mueff).sqrt() * c_inv_sqrt_y;
+            let ps_norm = ps.dot(&ps).sqrt();
+            let h_sigma =
+                ps_norm / (1.0 - (1.0 - cs).powi(2 * (generation as i32 + 1))).sqrt() / chi_n
+                    < 1.4 + 2.0 / (nf + 1.0);
+            let h = if h_sigma { 1.0 } else { 0.0 };
+            pc = (1.0 - cc) * &pc + h * (cc * (2.0 - cc) * mueff).sqrt() * &y_w;
+
+            // 共分散行列と歩幅を更新する
+            let rank_one = outer(&pc, &pc);
+            let rank_mu = best
+                .iter()
+                .zip(&weights)
+                .fold(Array2::zeros((n, n)), |acc, ((y, _), w)| {
+                    acc + *w * outer(y, y)
+                });
+            c = (1.0 - c1 - cmu + (1.0 - h) * c1 * cc * (2.0 - cc)) * c
+                + c1 * rank_one
+                + cmu * rank_mu;
+            sigma *= ((cs / damps) * (ps_norm / chi_n - 1.0)).exp();
+
+            let (eigenvalues, eigenvectors) = symmetric_eigen(&c);
+            d = eigenvalues.mapv(|e| e.max(1e-20).sqrt());
+            b = eigenvectors;
+            tracing::info!(
+                "Generation {} Best {} Sigma {}",
+                generation,
+                samples[0].1,
+                sigma
+            );
+            if sigma * d.fold(0.0, |a: f64, x| a.max(*x)) < 1e-12 {
+                break;
+            }
+        }
+        tracker.solution()
+    }
+}
+
+fn outer(a: &Array1<f64>, b: &Array1<f64>) -> Array2<f64> {
+    let a = a.view().insert_axis(Axis(1));
+    let b = b.view().insert_axis(Axis(0));
+    a.dot(&b)
+}
+
+/// 対称行列の固有値と、それを列にもつ固有ベクトルの行列。ヤコビ法で求める。
+fn symmetric_eigen(matrix: &Array2<f64>) -> (Array1<f64>, Array2<f64>) {
+    let n = matrix.nrows();
+    let mut a = matrix.clone();
+    let mut v = Array2::<f64>::eye(n);
+    for _ in 0..100 {
+        let off_diagonal = (0..n)
+            .flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[[i, j]] * a[[i, j]])
+            .sum::<f64>();
+        if off_diagonal < 1e-30 {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[[p, q]].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[[q, q]] - a[[p, p]]) / (2.0 * a[[p, q]]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let cos = 1.0 / (t * t + 1.0).sqrt();
+                let sin = t * cos;
+                for k in 0..n {
+                    let (akp, akq) = (a[[k, p]], a[[k, q]]);
+                    a[[k, p]] = cos * akp - sin * akq;
+                    a[[k, q]] = sin * akp + cos * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[[p, k]], a[[q, k]]);
+                    a[[p, k]] = cos * apk - sin * aqk;
+                    a[[q, k]] = sin * apk + cos * aqk;
+                }
+                for k in 0..n {
+                    let (vkp, vkq) = (v[[k, p]], v[[k, q]]);
+                    v[[k, p]] = cos * vkp - sin * vkq;
+                    v[[k, q]] = sin * vkp + cos * vkq;
+                }
+            }
+        }
+    }
+    (a.diag().to_owned(), v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{rastrigin, rosenbrock, sphere};
+    use super::*;
+
+    #[test]
+    fn test_symmetric_eigen() {
+        let matrix = ndarray::arr2(&[[2.0, 1.0, 0.0], [1.0, 3.0, 1.0], [0.0, 1.0, 4.0]]);
+        let (values, vectors) = symmetric_eigen(&matrix);
+        let restored = vectors.dot(&Array2::from_diag(&values)).dot(&vectors.t());
+        assert!((restored - &matrix).iter().all(|x| x.abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_benchmarks() {
+        let sphere = CmaEs::new(Bounds::uniform(10, -5.0, 5.0), 500)
+            .seed(0)
+            .minimize(&sphere);
+        assert!(sphere.value < 1e-10, "{:?}", sphere);
+        let rosenbrock = CmaEs::new(Bounds::uniform(5, -2.0, 2.0), 2000)
+            .seed(0)
+            .minimize(&rosenbrock);
+        assert!(rosenbrock.value < 1e-8, "{:?}", rosenbrock);
+        let rastrigin = CmaEs::new(Bounds::uniform(3, -5.12, 5.12), 500)
+            .population(100)
+            .seed(0)
+            .minimize(&rastrigin);
+        assert!(rastrigin.value < 1e-8, "{:?}", rastrigin);
+    }
+
+    #[test]
+    fn test_no_generations() {
+        let solution = CmaEs::new(Bounds::uniform(2, -1.0, 1.0), 0)
+            .seed(0)
+            .minimize(&sphere);
+        assert_eq!(solution.evaluations, 1);
+        assert_eq!(solution.value, sphere(&solution.x));
+    }
+}
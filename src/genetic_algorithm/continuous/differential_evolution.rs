@@ -0,0 +1,124 @@
+//! 差分進化 (DE/rand/1/bin)
+use rand::{seq::index, Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use super::{Bounds, Optimizer, Solution, Tracker};
+
+/// 集団から選んだ 3 点 a, b, c で a + weight * (b - c) を作り、
+/// 各変数を crossover の確率でもとの点と入れ替えた試行点が良ければ置き換える。
+pub struct DifferentialEvolution {
+    bounds: Bounds,
+    population_size: usize,
+    weight: f64,
+    crossover: f64,
+    generations: u32,
+    rng: ChaCha12Rng,
+}
+
+impl DifferentialEvolution {
+    /// 差分の重みは 0.8、交叉率は 0.9 から始める。集団は 4 個体以上いる。
+    pub fn new(bounds: Bounds, population_size: usize, generations: u32) -> Self {
+        assert!(population_size >= 4);
+        Self {
+            bounds,
+            population_size,
+            weight: 0.8,
+            crossover: 0.9,
+            generations,
+            rng: ChaCha12Rng::from_entropy(),
+        }
+    }
+
+    pub fn weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn crossover(mut self, crossover: f64) -> Self {
+        self.crossover = crossover;
+        self
+    }
+
+    /// 同じ seed なら同じ結果になる。
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self
+    }
+
+    /// i 番目の点の試行点
+    fn trial(&mut self, population: &[Vec<f64>], i: usize) -> Vec<f64> {
+        let (a, b, c) = loop {
+            let picked = index::sample(&mut self.rng, population.len(), 3);
+            if !picked.iter().any(|j| j == i) {
+                break (picked.index(0), picked.index(1), picked.index(2));
+            }
+        };
+        let n = self.bounds.dimension();
+        // 少なくとも 1 つの変数は差分から取る
+        let forced = self.rng.gen_range(0..n);
+        let mut trial = (0..n)
+            .map(|k| {
+                if k == forced || self.rng.gen_bool(self.crossover) {
+                    population[a][k] + self.weight * (population[b][k] - population[c][k])
+                } else {
+                    population[i][k]
+                }
+            })
+            .collect::<Vec<_>>();
+        self.bounds.clamp(&mut trial);
+        trial
+    }
+}
+
+impl Optimizer for DifferentialEvolution {
+    fn minimize(&mut self, objective: &dyn Fn(&[f64]) -> f64) -> Solution {
+        let mut tracker = Tracker::new(objective);
+        let mut population = (0..self.population_size)
+            .map(|_| self.bounds.sample(&mut self.rng))
+            .collect::<Vec<_>>();
+        let mut values = population
+            .iter()
+            .map(|x| tracker.evaluate(x))
+            .collect::<Vec<_>>();
+        for generation in 0..self.generations {
+            for i in 0..self.population_size {
+                let trial = self.trial(&population, i);
+                let value = tracker.evaluate(&trial);
+                if value <= values[i] {
+                    population[i] = trial;
+                    values[i] = value;
+                }
+            }
+            tracing::info!(
+                "Generation {} Best {}",
+                generation,
+                values.iter().copied().fold(f64::INFINITY, f64::min)
+            );
+        }
+        tracker.solution()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{rastrigin, rosenbrock, sphere};
+    use super::*;
+
+    #[test]
+    fn test_benchmarks() {
+        let sphere = DifferentialEvolution::new(Bounds::uniform(10, -5.0, 5.0), 40, 1000)
+            .seed(0)
+            .minimize(&sphere);
+        assert!(sphere.value < 1e-8, "{:?}", sphere);
+        assert_eq!(sphere.evaluations, 40 * 1001);
+        let rosenbrock = DifferentialEvolution::new(Bounds::uniform(3, -2.0, 2.0), 30, 1000)
+            .seed(0)
+            .minimize(&rosenbrock);
+        assert!(rosenbrock.value < 1e-6, "{:?}", rosenbrock);
+        let rastrigin = DifferentialEvolution::new(Bounds::uniform(5, -5.12, 5.12), 50, 1000)
+            .crossover(0.1)
+            .seed(0)
+            .minimize(&rastrigin);
+        assert!(rastrigin.value < 1e-6, "{:?}", rastrigin);
+    }
+}
@@ -0,0 +1,146 @@
+//! 歩幅が自己適応する (μ,λ) と (μ+λ) の進化戦略
+use ndarray_rand::rand_distr::StandardNormal;
+use ordered_float::OrderedFloat;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use super::{Bounds, Optimizer, Solution, Tracker};
+
+/// 点と、変数ごとの突然変異の歩幅
+#[derive(Debug, Clone)]
+struct Individual {
+    x: Vec<f64>,
+    sigma: Vec<f64>,
+    value: f64,
+}
+
+/// μ 個の親から λ 個の子を作り、次の親 μ 個を選ぶ。
+/// (μ,λ) は子だけから、(μ+λ) は親と子を合わせた中から選ぶ。
+/// 歩幅も遺伝子の一部として対数正規分布で突然変異させる。
+pub struct EvolutionStrategy {
+    bounds: Bounds,
+    mu: usize,
+    lambda: usize,
+    plus: bool,
+    generations: u32,
+    rng: ChaCha12Rng,
+}
+
+impl EvolutionStrategy {
+    fn new(bounds: Bounds, mu: usize, lambda: usize, plus: bool, generations: u32) -> Self {
+        Self {
+            bounds,
+            mu,
+            lambda,
+            plus,
+            generations,
+            rng: ChaCha12Rng::from_entropy(),
+        }
+    }
+
+    /// (μ,λ)。λ は μ 以上でなければならない。
+    pub fn comma(bounds: Bounds, mu: usize, lambda: usize, generations: u32) -> Self {
+        assert!(mu >= 1 && lambda >= mu);
+        Self::new(bounds, mu, lambda, false, generations)
+    }
+
+    /// (μ+λ)。親が残れるので最良の値は悪くならない。
+    pub fn plus(bounds: Bounds, mu: usize, lambda: usize, generations: u32) -> Self {
+        assert!(mu >= 1 && lambda >= 1);
+        Self::new(bounds, mu, lambda, true, generations)
+    }
+
+    /// 同じ seed なら同じ結果になる。
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self
+    }
+
+    /// 2 つの親の中間をとり、歩幅、点の順に突然変異させる。
+    fn offspring(&mut self, parent1: &Individual, parent2: &Individual) -> (Vec<f64>, Vec<f64>) {
+        let n = self.bounds.dimension() as f64;
+        let tau_global = 1.0 / (2.0 * n).sqrt();
+        let tau_local = 1.0 / (2.0 * n.sqrt()).sqrt();
+        let global: f64 = self.rng.sample(StandardNormal);
+        let mut x = Vec::new();
+        let mut sigma = Vec::new();
+        for i in 0..self.bounds.dimension() {
+            let local: f64 = self.rng.sample(StandardNormal);
+            let s = (parent1.sigma[i] + parent2.sigma[i]) / 2.0
+                * (tau_global * global + tau_local * local).exp();
+            let s = s.clamp(1e-12 * self.bounds.width(i), self.bounds.width(i));
+            let step: f64 = self.rng.sample(StandardNormal);
+            x.push((parent1.x[i] + parent2.x[i]) / 2.0 + s * step);
+            sigma.push(s);
+        }
+        self.bounds.clamp(&mut x);
+        (x, sigma)
+    }
+}
+
+impl Optimizer for EvolutionStrategy {
+    fn minimize(&mut self, objective: &dyn Fn(&[f64]) -> f64) -> Solution {
+        let mut tracker = Tracker::new(objective);
+        let sigma = (0..self.bounds.dimension())
+            .map(|i| self.bounds.width(i) / 10.0)
+            .collect::<Vec<_>>();
+        let mut parents = (0..self.mu)
+            .map(|_| {
+                let x = self.bounds.sample(&mut self.rng);
+                let value = tracker.evaluate(&x);
+                Individual {
+                    x,
+                    sigma: sigma.clone(),
+                    value,
+                }
+            })
+            .collect::<Vec<_>>();
+        for generation in 0..self.generations {
+            let mut offspring = Vec::new();
+            for _ in 0..self.lambda {
+                let parent1 = &parents[self.rng.gen_range(0..self.mu)];
+                let parent2 = &parents[self.rng.gen_range(0..self.mu)];
+                let (x, sigma) = self.offspring(parent1, parent2);
+                let value = tracker.evaluate(&x);
+                offspring.push(Individual { x, sigma, value });
+            }
+            if self.plus {
+                offspring.append(&mut parents);
+            }
+            offspring.sort_by_key(|i| OrderedFloat(i.value));
+            offspring.truncate(self.mu);
+            parents = offspring;
+            tracing::info!("Generation {} Best {}", generation, parents[0].value);
+        }
+        tracker.solution()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{rastrigin, sphere};
+    use super::*;
+
+    #[test]
+    fn test_sphere() {
+        let bounds = Bounds::uniform(10, -5.0, 5.0);
+        let comma = EvolutionStrategy::comma(bounds.clone(), 5, 35, 300)
+            .seed(0)
+            .minimize(&sphere);
+        assert!(comma.value < 1e-6, "{:?}", comma);
+        let plus = EvolutionStrategy::plus(bounds, 5, 35, 300)
+            .seed(0)
+            .minimize(&sphere);
+        assert!(plus.value < 1e-6, "{:?}", plus);
+        assert_eq!(plus.evaluations, 5 + 35 * 300);
+    }
+
+    #[test]
+    fn test_rastrigin() {
+        let bounds = Bounds::uniform(2, -5.12, 5.12);
+        let solution = EvolutionStrategy::plus(bounds, 20, 100, 200)
+            .seed(1)
+            .minimize(&rastrigin);
+        assert!(solution.value < 1e-6, "{:?}", solution);
+    }
+}
@@ -0,0 +1,406 @@
+//! 木で表した式を進化させる遺伝的プログラミング
+use std::fmt;
+
+use rand::Rng;
+
+pub mod symbolic_regression;
+
+/// 式の中の関数。割り算や対数は定義域の外でも値を返すように守ってある。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Add,
+    Sub,
+    Mul,
+    /// 分母がほぼ 0 なら 1
+    Div,
+    Sin,
+    Cos,
+    /// 引数が大きすぎるときは exp(50)
+    Exp,
+    /// ln |x|。x が 0 なら 0
+    Log,
+}
+
+impl Function {
+    pub fn arity(&self) -> usize {
+        match self {
+            Function::Add | Function::Sub | Function::Mul | Function::Div => 2,
+            Function::Sin | Function::Cos | Function::Exp | Function::Log => 1,
+        }
+    }
+
+    fn apply(&self, args: &[f64]) -> f64 {
+        match self {
+            Function::Add => args[0] + args[1],
+            Function::Sub => args[0] - args[1],
+            Function::Mul => args[0] * args[1],
+            Function::Div if args[1].abs() < 1e-9 => 1.0,
+            Function::Div => args[0] / args[1],
+            Function::Sin => args[0].sin(),
+            Function::Cos => args[0].cos(),
+            Function::Exp => args[0].min(50.0).exp(),
+            Function::Log if args[0] == 0.0 => 0.0,
+            Function::Log => args[0].abs().ln(),
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Function::Add => "+",
+            Function::Sub => "-",
+            Function::Mul => "*",
+            Function::Div => "/",
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Exp => "exp",
+            Function::Log => "log",
+        }
+    }
+}
+
+/// 木の節。関数の引数はその後ろに前置記法で続く。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Node {
+    Function(Function),
+    /// 入力の何番目の変数か
+    Variable(usize),
+    Constant(f64),
+}
+
+impl Node {
+    fn arity(&self) -> usize {
+        match self {
+            Node::Function(function) => function.arity(),
+            _ => 0,
+        }
+    }
+}
+
+/// 式を組み立てる関数と終端記号
+#[derive(Debug, Clone, PartialEq)]
+pub struct Primitives {
+    functions: Vec<Function>,
+    variables: Vec<String>,
+    /// 終端記号として作る定数の範囲。None なら定数を使わない。
+    constants: Option<(f64, f64)>,
+}
+
+impl Primitives {
+    /// 関数は四則演算、定数は -1 から 1 で始める。
+    pub fn new(variables: &[&str]) -> Self {
+        Self {
+            functions: vec![Function::Add, Function::Sub, Function::Mul, Function::Div],
+            variables: variables.iter().map(|v| v.to_string()).collect(),
+            constants: Some((-1.0, 1.0)),
+        }
+    }
+
+    pub fn functions(mut self, functions: &[Function]) -> Self {
+        self.functions = functions.to_vec();
+        self
+    }
+
+    /// 変数がないときは、終端記号がなくなるので None にできない。
+    pub fn constants(mut self, constants: Option<(f64, f64)>) -> Self {
+        assert!(constants.is_some() || !self.variables.is_empty());
+        self.constants = constants;
+        self
+    }
+
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    fn terminal<R: Rng + ?Sized>(&self, rng: &mut R) -> Node {
+        let choices = self.variables.len() + usize::from(self.constants.is_some());
+        match (rng.gen_range(0..choices), self.constants) {
+            (i, _) if i < self.variables.len() => Node::Variable(i),
+            (_, Some((low, high))) => Node::Constant(rng.gen_range(low..=high)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// 前置記法で並べた式の木
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    /// nodes は前置記法で正しく閉じていなければならない。
+    pub fn new(nodes: Vec<Node>) -> Self {
+        // 足りない並びで subtree_end がはみ出さないよう、ここで数える
+        let mut needed = 1;
+        for node in &nodes {
+            assert!(needed > 0, "nodes continue after the tree is closed");
+            needed = needed + node.arity() - 1;
+        }
+        assert!(needed == 0, "nodes end before the tree is closed");
+        Self { nodes }
+    }
+
+    /// 深さが depth 以下の木。full なら葉がすべて深さ depth にそろう。
+    pub fn random<R: Rng + ?Sized>(
+        primitives: &Primitives,
+        depth: usize,
+        full: bool,
+        rng: &mut R,
+    ) -> Self {
+        let mut nodes = Vec::new();
+        Self::generate(primitives, depth, full, rng, &mut nodes);
+        Self { nodes }
+    }
+
+    fn generate<R: Rng + ?Sized>(
+        primitives: &Primitives,
+        depth: usize,
+        full: bool,
+        rng: &mut R,
+        nodes: &mut Vec<Node>,
+    ) {
+        let terminals = primitives.variables.len() + usize::from(primitives.constants.is_some());
+        let leaf = depth == 0
+            || primitives.functions.is_empty()
+            || (!full && rng.gen_range(0..terminals + primitives.functions.len()) < terminals);
+        if leaf {
+            nodes.push(primitives.terminal(rng));
+            return;
+        }
+        let function = primitives.functions[rng.gen_range(0..primitives.functions.len())];
+        nodes.push(Node::Function(function));
+        for _ in 0..function.arity() {
+            Self::generate(primitives, depth - 1, full, rng, nodes);
+        }
+    }
+
+    /// 深さを 1 から max_depth まで散らし、半分を full、半分を grow で作る (ramped half-and-half)。
+    pub fn ramped<R: Rng + ?Sized>(
+        primitives: &Primitives,
+        count: usize,
+        max_depth: usize,
+        rng: &mut R,
+    ) -> Vec<Self> {
+        (0..count)
+            .map(|i| Self::random(primitives, 1 + i % max_depth.max(1), i % 2 == 0, rng))
+            .collect()
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// 節の数
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 根だけなら 0
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        // まだ埋まっていない引数の深さ
+        let mut open = vec![0];
+        for node in &self.nodes {
+            let d = open.pop().unwrap();
+            depth = depth.max(d);
+            open.extend(std::iter::repeat_n(d + 1, node.arity()));
+        }
+        depth
+    }
+
+    /// start から始まる部分木の終わりの次の位置
+    fn subtree_end(&self, start: usize) -> usize {
+        let mut needed = 1;
+        let mut end = start;
+        while needed > 0 {
+            needed = needed + self.nodes[end].arity() - 1;
+            end += 1;
+        }
+        end
+    }
+
+    /// 変数に inputs を入れたときの値
+    pub fn evaluate(&self, inputs: &[f64]) -> f64 {
+        let mut stack = Vec::new();
+        for node in self.nodes.iter().rev() {
+            let value = match node {
+                Node::Function(function) => {
+                    let args = (0..function.arity())
+                        .map(|_| stack.pop().unwrap())
+                        .collect::<Vec<f64>>();
+                    function.apply(&args)
+                }
+                Node::Variable(i) => inputs[*i],
+                Node::Constant(c) => *c,
+            };
+            stack.push(value);
+        }
+        stack.pop().unwrap()
+    }
+
+    /// start からの部分木を subtree に入れ替えた木
+    fn replace(&self, start: usize, subtree: &[Node]) -> Self {
+        let end = self.subtree_end(start);
+        let mut nodes = self.nodes[..start].to_vec();
+        nodes.extend_from_slice(subtree);
+        nodes.extend_from_slice(&self.nodes[end..]);
+        Self { nodes }
+    }
+
+    /// 部分木の交叉。それぞれの木から選んだ部分木を入れ替える。
+    pub fn crossover<R: Rng + ?Sized>(&self, other: &Self, rng: &mut R) -> (Self, Self) {
+        let (start1, start2) = (rng.gen_range(0..self.len()), rng.gen_range(0..other.len()));
+        let subtree1 = &self.nodes[start1..self.subtree_end(start1)];
+        let subtree2 = &other.nodes[start2..other.subtree_end(start2)];
+        (
+            self.replace(start1, subtree2),
+            other.replace(start2, subtree1),
+        )
+    }
+
+    /// 部分木の突然変異。選んだ部分木を深さ depth 以下の新しい木に置き換える。
+    pub fn mutate<R: Rng + ?Sized>(&mut self, primitives: &Primitives, depth: usize, rng: &mut R) {
+        let start = rng.gen_range(0..self.len());
+        let subtree = Self::random(primitives, rng.gen_range(0..=depth), false, rng);
+        *self = self.replace(start, &subtree.nodes);
+    }
+
+    /// index の部分木を中置記法で書き、その次の位置を返す。
+    fn write(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        index: usize,
+        variables: &[String],
+    ) -> Result<usize, fmt::Error> {
+        match self.nodes[index] {
+            Node::Function(function) if function.arity() == 2 => {
+                write!(f, "(")?;
+                let next = self.write(f, index + 1, variables)?;
+                write!(f, " {} ", function.symbol())?;
+                let next = self.write(f, next, variables)?;
+                write!(f, ")")?;
+                Ok(next)
+            }
+            Node::Function(function) => {
+                write!(f, "{}(", function.symbol())?;
+                let next = self.write(f, index + 1, variables)?;
+                write!(f, ")")?;
+                Ok(next)
+            }
+            Node::Variable(i) => match variables.get(i) {
+                Some(name) => write!(f, "{}", name).map(|_| index + 1),
+                None => write!(f, "x{}", i).map(|_| index + 1),
+            },
+            Node::Constant(c) => write!(f, "{:.3}", c).map(|_| index + 1),
+        }
+    }
+
+    /// 変数に名前をつけて表示する。
+    pub fn display<'a>(&'a self, variables: &'a [String]) -> impl fmt::Display + 'a {
+        Named {
+            tree: self,
+            variables,
+        }
+    }
+}
+
+struct Named<'a> {
+    tree: &'a Tree,
+    variables: &'a [String],
+}
+
+impl fmt::Display for Named<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.tree.write(f, 0, self.variables).map(|_| ())
+    }
+}
+
+/// 変数は x0, x1, ... と表示する。
+impl fmt::Display for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(&[]).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// (x0 - 2) * sin(x1)
+    fn tree() -> Tree {
+        Tree::new(vec![
+            Node::Function(Function::Mul),
+            Node::Function(Function::Sub),
+            Node::Variable(0),
+            Node::Constant(2.0),
+            Node::Function(Function::Sin),
+            Node::Variable(1),
+        ])
+    }
+
+    #[test]
+    fn test_tree() {
+        let tree = tree();
+        assert_eq!(tree.depth(), 2);
+        assert_eq!(tree.subtree_end(1), 4);
+        assert_eq!(tree.to_string(), "((x0 - 2.000) * sin(x1))");
+        let variables = ["a".to_string(), "b".to_string()];
+        assert_eq!(
+            tree.display(&variables).to_string(),
+            "((a - 2.000) * sin(b))"
+        );
+        let x1 = std::f64::consts::FRAC_PI_2;
+        assert!((tree.evaluate(&[5.0, x1]) - 3.0).abs() < 1e-12);
+        assert_eq!(
+            Tree::new(vec![
+                Node::Function(Function::Div),
+                Node::Constant(1.0),
+                Node::Constant(0.0)
+            ])
+            .evaluate(&[]),
+            1.0
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "before the tree is closed")]
+    fn test_truncated_tree() {
+        Tree::new(vec![Node::Function(Function::Add)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "after the tree is closed")]
+    fn test_trailing_nodes() {
+        Tree::new(vec![Node::Variable(0), Node::Variable(1)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_no_terminals() {
+        Primitives::new(&[]).constants(None);
+    }
+
+    #[test]
+    fn test_operators_keep_trees_valid() {
+        let primitives =
+            Primitives::new(&["x", "y"]).functions(&[Function::Add, Function::Mul, Function::Sin]);
+        let mut rng = StdRng::seed_from_u64(0);
+        let full = Tree::random(&primitives, 3, true, &mut rng);
+        assert_eq!(full.depth(), 3);
+        let trees = Tree::ramped(&primitives, 20, 4, &mut rng);
+        assert!(trees.iter().all(|t| t.depth() <= 4));
+        for pair in trees.chunks(2) {
+            let (mut child1, child2) = pair[0].crossover(&pair[1], &mut rng);
+            assert_eq!(child1.len() + child2.len(), pair[0].len() + pair[1].len());
+            child1.mutate(&primitives, 2, &mut rng);
+            for child in [child1, child2] {
+                assert_eq!(child.subtree_end(0), child.len());
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! 記号回帰。データに合う式を遺伝的プログラミングで探す。
+use std::marker::PhantomData;
+
+use rand::Rng;
+
+use super::{Primitives, Tree};
+use crate::genetic_algorithm::Chromosome;
+
+/// 入力の行と目標の値、それに式の大きさの制限
+#[derive(Debug, Clone)]
+pub struct SymbolicRegression {
+    primitives: Primitives,
+    inputs: Vec<Vec<f64>>,
+    targets: Vec<f64>,
+    max_depth: usize,
+    parsimony: f64,
+}
+
+impl SymbolicRegression {
+    /// 木の深さは既定で 8 まで、節 1 つあたりの罰則は 0.001。
+    pub fn new(primitives: Primitives, inputs: Vec<Vec<f64>>, targets: Vec<f64>) -> Self {
+        assert_eq!(inputs.len(), targets.len());
+        Self {
+            primitives,
+            inputs,
+            targets,
+            max_depth: 8,
+            parsimony: 0.001,
+        }
+    }
+
+    /// 交叉や突然変異でこれより深くなった子は捨てて親を残す。
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// 適応度から節 1 つにつき引く値。式が無駄に膨らむのを抑える。
+    pub fn parsimony(mut self, parsimony: f64) -> Self {
+        self.parsimony = parsimony;
+        self
+    }
+
+    pub fn primitives(&self) -> &Primitives {
+        &self.primitives
+    }
+
+    pub fn mean_squared_error(&self, tree: &Tree) -> f64 {
+        self.inputs
+            .iter()
+            .zip(&self.targets)
+            .map(|(inputs, target)| (tree.evaluate(inputs) - target).powi(2))
+            .sum::<f64>()
+            / self.targets.len() as f64
+    }
+}
+
+/// Program<P> が使う問題。Chromosome::random_instance は引数をとらないので、型から引けるようにする。
+pub trait RegressionProblem: 'static {
+    fn get() -> &'static SymbolicRegression;
+}
+
+/// P の関数と変数でできた式。適応度は平均二乗誤差と節の数の罰則を引いたもの。
+pub struct Program<P> {
+    pub tree: Tree,
+    problem: PhantomData<fn() -> P>,
+}
+
+impl<P: RegressionProblem> Program<P> {
+    pub fn new(tree: Tree) -> Self {
+        Self {
+            tree,
+            problem: PhantomData,
+        }
+    }
+
+    /// ramped half-and-half で作った初期集団
+    pub fn population<R: Rng + ?Sized>(size: usize, rng: &mut R) -> Vec<Self> {
+        let problem = P::get();
+        Tree::ramped(&problem.primitives, size, problem.max_depth.min(6), rng)
+            .into_iter()
+            .map(Self::new)
+            .collect()
+    }
+
+    /// 深さの制限を超えた子は代わりに親を返す。
+    fn limit(&self, child: Tree) -> Self {
+        if child.depth() <= P::get().max_depth {
+            Self::new(child)
+        } else {
+            self.clone()
+        }
+    }
+}
+
+impl<P> Clone for Program<P> {
+    fn clone(&self) -> Self {
+        Self {
+            tree: self.tree.clone(),
+            problem: PhantomData,
+        }
+    }
+}
+
+impl<P: RegressionProblem> Chromosome for Program<P> {
+    fn fitness(&self) -> f64 {
+        let problem = P::get();
+        let error = problem.mean_squared_error(&self.tree);
+        if error.is_finite() {
+            -error - problem.parsimony * self.tree.len() as f64
+        } else {
+            f64::MIN
+        }
+    }
+
+    fn random_instance<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let problem = P::get();
+        let depth = rng.gen_range(1..=problem.max_depth.min(6));
+        Self::new(Tree::random(
+            &problem.primitives,
+            depth,
+            rng.gen_bool(0.5),
+            rng,
+        ))
+    }
+
+    fn crossover<R: Rng + ?Sized>(&self, other: &Self, rng: &mut R) -> (Self, Self) {
+        let (child1, child2) = self.tree.crossover(&other.tree, rng);
+        (self.limit(child1), other.limit(child2))
+    }
+
+    fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let mut child = self.tree.clone();
+        child.mutate(&P::get().primitives, 3, rng);
+        *self = self.limit(child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use super::super::Function;
+    use super::*;
+    use crate::genetic_algorithm::operators::{Elitism, Tournament};
+    use crate::genetic_algorithm::GeneticAlgorithm;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// y = x^2 + x
+    struct Quadratic;
+
+    impl RegressionProblem for Quadratic {
+        fn get() -> &'static SymbolicRegression {
+            static PROBLEM: OnceLock<SymbolicRegression> = OnceLock::new();
+            PROBLEM.get_or_init(|| {
+                let xs = (-10..=10).map(|x| x as f64 / 5.0).collect::<Vec<_>>();
+                SymbolicRegression::new(
+                    Primitives::new(&["x"])
+                        .functions(&[Function::Add, Function::Sub, Function::Mul])
+                        .constants(None),
+                    xs.iter().map(|x| vec![*x]).collect(),
+                    xs.iter().map(|x| x * x + x).collect(),
+                )
+                .max_depth(5)
+            })
+        }
+    }
+
+    #[test]
+    fn test_rediscover_quadratic() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let population = Program::<Quadratic>::population(200, &mut rng);
+        let (best, _) = GeneticAlgorithm::new(population, -0.01, 30, 0.2, 0.9)
+            .selection(Tournament::new(5))
+            .replacement(Elitism::new(2))
+            .seed(1)
+            .run();
+        assert!(
+            Quadratic::get().mean_squared_error(&best.tree) < 1e-12,
+            "{}",
+            best.tree
+        );
+        assert!(best.tree.depth() <= 5);
+    }
+}
@@ -0,0 +1,259 @@
+//! 島モデル。いくつかの集団を別々に進化させ、ときどき良い個体を移住させる。
+use std::thread;
+
+use ordered_float::OrderedFloat;
+
+use super::{Chromosome, GenerationStats, GeneticAlgorithm, Individual, Termination};
+
+/// 移住の経路
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// i 番目の島から i + 1 番目の島へ
+    Ring,
+    /// すべての島からほかのすべての島へ
+    FullyConnected,
+}
+
+impl Topology {
+    fn destinations(&self, island: usize, islands: usize) -> Vec<usize> {
+        match self {
+            Topology::Ring if islands > 1 => vec![(island + 1) % islands],
+            Topology::Ring => Vec::new(),
+            Topology::FullyConnected => (0..islands).filter(|i| *i != island).collect(),
+        }
+    }
+}
+
+/// 島ごとの GeneticAlgorithm を interval 世代ずつ進め、そのたびに各島の上位 migrants 個を
+/// 移住先の島の下位と入れ替える。どれかの島が threshold に届くか、すべての島が
+/// 止まったら終わる。止まった島は移住に加わらない。
+pub struct Islands<C>
+where
+    C: Chromosome,
+{
+    islands: Vec<GeneticAlgorithm<C>>,
+    topology: Topology,
+    interval: u32,
+    migrants: usize,
+}
+
+impl<C> Islands<C>
+where
+    C: Chromosome,
+{
+    /// islands は空であってはならない。
+    pub fn new(
+        islands: Vec<GeneticAlgorithm<C>>,
+        topology: Topology,
+        interval: u32,
+        migrants: usize,
+    ) -> Self {
+        assert!(!islands.is_empty());
+        Self {
+            islands,
+            topology,
+            interval: interval.max(1),
+            migrants,
+        }
+    }
+
+    /// 島ごとの世代の統計は GeneticAlgorithm::history で見られる。
+    pub fn islands(&self) -> &[GeneticAlgorithm<C>] {
+        &self.islands
+    }
+
+    /// island を interval 世代進め、止まったらその理由を返す。
+    fn evolve(island: &mut GeneticAlgorithm<C>, interval: u32) -> Option<Termination> {
+        (0..interval).find_map(|_| island.step(&mut |_: &GenerationStats| {}))
+    }
+
+    /// まだ止まっていない島どうしで、topology に沿って個体を移す。
+    fn migrate(&mut self, finished: &[Option<Termination>]) {
+        let active = (0..self.islands.len())
+            .filter(|i| finished[*i].is_none())
+            .collect::<Vec<_>>();
+        let emigrants = active
+            .iter()
+            .map(|i| self.islands[*i].emigrants(self.migrants))
+            .collect::<Vec<_>>();
+        let mut immigrants: Vec<Vec<Individual<C>>> = vec![Vec::new(); active.len()];
+        for (from, emigrants) in emigrants.into_iter().enumerate() {
+            for to in self.topology.destinations(from, active.len()) {
+                immigrants[to].extend(emigrants.iter().cloned());
+            }
+        }
+        for (i, immigrants) in active.into_iter().zip(immigrants) {
+            self.islands[i].immigrate(immigrants);
+        }
+    }
+
+    /// どれかの島が threshold に届いたか、すべての島が止まったら、
+    /// すべての島を通しての最良の個体と止まった理由を返す。
+    /// 理由は threshold に届いた島があれば Threshold、なければ最良の個体を持つ島のもの。
+    fn result(&self, finished: &[Option<Termination>]) -> Option<(C, Termination)> {
+        let threshold = finished.contains(&Some(Termination::Threshold));
+        if !threshold && finished.iter().any(Option::is_none) {
+            return None;
+        }
+        let (i, (_, best)) = self
+            .islands
+            .iter()
+            .enumerate()
+            .filter_map(|(i, island)| island.best.as_ref().map(|best| (i, best)))
+            .max_by_key(|(_, (fitness, _))| OrderedFloat(*fitness))
+            .unwrap();
+        let termination = if threshold {
+            Termination::Threshold
+        } else {
+            finished[i].clone().unwrap()
+        };
+        Some((best.clone(), termination))
+    }
+
+    /// 島を 1 つずつ順に進める。
+    pub fn run(&mut self) -> (C, Termination) {
+        self.islands.iter_mut().for_each(GeneticAlgorithm::reset);
+        let mut finished = vec![None; self.islands.len()];
+        loop {
+            for (island, finished) in self.islands.iter_mut().zip(finished.iter_mut()) {
+                if finished.is_none() {
+                    *finished = Self::evolve(island, self.interval);
+                }
+            }
+            if let Some(result) = self.result(&finished) {
+                return result;
+            }
+            self.migrate(&finished);
+        }
+    }
+}
+
+impl<C> Islands<C>
+where
+    C: Chromosome + Send,
+{
+    /// 島ごとにスレッドを分けて進める。移住は全部の島がそろってから行う。
+    pub fn run_parallel(&mut self) -> (C, Termination) {
+        self.islands.iter_mut().for_each(GeneticAlgorithm::reset);
+        let mut finished = vec![None; self.islands.len()];
+        loop {
+            let interval = self.interval;
+            thread::scope(|s| {
+                for (island, finished) in self.islands.iter_mut().zip(finished.iter_mut()) {
+                    if finished.is_none() {
+                        s.spawn(move || *finished = Self::evolve(island, interval));
+                    }
+                }
+            });
+            if let Some(result) = self.result(&finished) {
+                return result;
+            }
+            self.migrate(&finished);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// 目標の値に近いほどよい
+    #[derive(Debug, Clone, PartialEq)]
+    struct Target(i64);
+
+    impl Chromosome for Target {
+        fn fitness(&self) -> f64 {
+            -(self.0 - 1000).abs() as f64
+        }
+
+        fn random_instance<R: Rng + ?Sized>(rng: &mut R) -> Self {
+            Self(rng.gen_range(0..100))
+        }
+
+        fn crossover<R: Rng + ?Sized>(&self, other: &Self, _rng: &mut R) -> (Self, Self) {
+            (Self((self.0 + other.0) / 2), Self(self.0.max(other.0)))
+        }
+
+        fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+            self.0 += rng.gen_range(-5..=20);
+        }
+    }
+
+    fn islands(topology: Topology) -> Islands<Target> {
+        islands_with(topology, 0.0, [200; 3])
+    }
+
+    fn islands_with(
+        topology: Topology,
+        threshold: f64,
+        max_generations: [u32; 3],
+    ) -> Islands<Target> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let islands = max_generations
+            .into_iter()
+            .zip(0..)
+            .map(|(max_generations, i)| {
+                let population = (0..10).map(|_| Target::random_instance(&mut rng)).collect();
+                GeneticAlgorithm::new(population, threshold, max_generations, 0.5, 0.5).seed(i)
+            })
+            .collect();
+        Islands::new(islands, topology, 5, 2)
+    }
+
+    #[test]
+    fn test_destinations() {
+        assert_eq!(Topology::Ring.destinations(2, 3), vec![0]);
+        assert_eq!(Topology::FullyConnected.destinations(1, 3), vec![0, 2]);
+        assert!(Topology::Ring.destinations(0, 1).is_empty());
+    }
+
+    #[test]
+    fn test_islands() {
+        for topology in [Topology::Ring, Topology::FullyConnected] {
+            let serial = islands(topology).run();
+            assert_eq!(serial, (Target(1000), Termination::Threshold));
+            // 島ごとの乱数は別々なので、スレッドに分けても結果は同じ
+            assert_eq!(islands(topology).run_parallel(), serial);
+        }
+    }
+
+    #[test]
+    fn test_max_generations() {
+        // 届かない threshold。最初の島だけ早く止まる。history には 0 世代目も入る。
+        let mut islands = islands_with(Topology::Ring, 1.0, [10, 30, 30]);
+        let (_, termination) = islands.run();
+        assert_eq!(termination, Termination::MaxGenerations);
+        let generations = islands
+            .islands()
+            .iter()
+            .map(|island| island.history.len())
+            .collect::<Vec<_>>();
+        assert_eq!(generations, [11, 31, 31]);
+    }
+
+    #[test]
+    fn test_migrate_skips_finished() {
+        let mut islands = islands(Topology::FullyConnected);
+        for island in islands.islands.iter_mut() {
+            island.reset();
+            Islands::evolve(island, 1);
+        }
+        let chromosomes = |islands: &Islands<Target>, i: usize| {
+            islands.islands[i]
+                .population
+                .iter()
+                .map(|x| x.chromosome.clone())
+                .collect::<Vec<_>>()
+        };
+        let before = chromosomes(&islands, 0);
+        islands.migrate(&[Some(Termination::MaxGenerations), None, None]);
+        assert_eq!(chromosomes(&islands, 0), before);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_no_islands() {
+        Islands::<Target>::new(Vec::new(), Topology::Ring, 5, 2);
+    }
+}
@@ -0,0 +1,294 @@
+//! NSGA-II による多目的最適化
+use ordered_float::OrderedFloat;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use super::operators::{Crossover, Mutation};
+
+/// 複数の目的をもつ染色体。目的はすべて大きいほどよい。
+pub trait MultiObjective: Clone {
+    fn objectives(&self) -> Vec<f64>;
+}
+
+/// a がどの目的でも b 以上で、少なくとも 1 つで b より大きい。
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x >= y) && a.iter().zip(b).any(|(x, y)| x > y)
+}
+
+/// 非優越ソート。どれにも優越されない個体の添字から順に、前線ごとに返す。
+pub fn non_dominated_sort(objectives: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let n = objectives.len();
+    let mut dominated = vec![Vec::new(); n];
+    let mut domination_count = vec![0; n];
+    for i in 0..n {
+        for j in 0..n {
+            if dominates(&objectives[i], &objectives[j]) {
+                dominated[i].push(j);
+            } else if dominates(&objectives[j], &objectives[i]) {
+                domination_count[i] += 1;
+            }
+        }
+    }
+    let mut fronts = Vec::new();
+    let mut front = (0..n)
+        .filter(|i| domination_count[*i] == 0)
+        .collect::<Vec<_>>();
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for i in &front {
+            for j in &dominated[*i] {
+                domination_count[*j] -= 1;
+                if domination_count[*j] == 0 {
+                    next.push(*j);
+                }
+            }
+        }
+        fronts.push(front);
+        front = next;
+    }
+    fronts
+}
+
+/// 前線の中での混雑距離。両端は無限大で、大きいほど周りが空いている。
+/// 返す距離は front と同じ順。
+#[allow(clippy::needless_range_loop)]
+pub fn crowding_distance(objectives: &[Vec<f64>], front: &[usize]) -> Vec<f64> {
+    let mut distance = vec![0.0; front.len()];
+    let Some(first) = front.first() else {
+        return distance;
+    };
+    for m in 0..objectives[*first].len() {
+        let mut order = (0..front.len()).collect::<Vec<_>>();
+        order.sort_by_key(|k| OrderedFloat(objectives[front[*k]][m]));
+        let (low, high) = (
+            objectives[front[order[0]]][m],
+            objectives[front[order[order.len() - 1]]][m],
+        );
+        distance[order[0]] = f64::INFINITY;
+        distance[order[order.len() - 1]] = f64::INFINITY;
+        if high == low {
+            continue;
+        }
+        for w in order.windows(3) {
+            distance[w[1]] +=
+                (objectives[front[w[2]]][m] - objectives[front[w[0]]][m]) / (high - low);
+        }
+    }
+    distance
+}
+
+/// 前線の順位と混雑距離。順位が小さく、同じなら距離が大きいほうがよい。
+fn rank_and_crowding(objectives: &[Vec<f64>]) -> (Vec<Vec<usize>>, Vec<(usize, f64)>) {
+    let fronts = non_dominated_sort(objectives);
+    let mut ranking = vec![(0, 0.0); objectives.len()];
+    for (rank, front) in fronts.iter().enumerate() {
+        for (i, distance) in front.iter().zip(crowding_distance(objectives, front)) {
+            ranking[*i] = (rank, distance);
+        }
+    }
+    (fronts, ranking)
+}
+
+fn better(a: (usize, f64), b: (usize, f64)) -> bool {
+    a.0 < b.0 || (a.0 == b.0 && a.1 > b.1)
+}
+
+/// NSGA-II。親と子を合わせた集団から、前線の順位と混雑距離で次の世代を選ぶ。
+pub struct Nsga2<C>
+where
+    C: MultiObjective,
+{
+    population: Vec<C>,
+    generations: u32,
+    mutation_chance: f64,
+    crossover_chance: f64,
+    crossover: Box<dyn Crossover<C>>,
+    mutation: Box<dyn Mutation<C>>,
+    rng: ChaCha12Rng,
+}
+
+impl<C> Nsga2<C>
+where
+    C: MultiObjective,
+{
+    /// initial_population は空であってはならない。
+    pub fn new(
+        initial_population: Vec<C>,
+        generations: u32,
+        mutation_chance: f64,
+        crossover_chance: f64,
+        crossover: impl Crossover<C> + 'static,
+        mutation: impl Mutation<C> + 'static,
+    ) -> Self {
+        assert!(!initial_population.is_empty());
+        Self {
+            population: initial_population,
+            generations,
+            mutation_chance,
+            crossover_chance,
+            crossover: Box::new(crossover),
+            mutation: Box::new(mutation),
+            rng: ChaCha12Rng::from_entropy(),
+        }
+    }
+
+    /// 同じ seed と初期集団なら同じ結果になる。
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self
+    }
+
+    /// 2 個体のトーナメントで親を選び、集団と同じ数の子を作る。
+    fn offspring(&mut self, ranking: &[(usize, f64)]) -> Vec<C> {
+        let rng = &mut self.rng;
+        let n = self.population.len();
+        let pick = |rng: &mut ChaCha12Rng| {
+            let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+            if better(ranking[b], ranking[a]) {
+                b
+            } else {
+                a
+            }
+        };
+        let mut offspring = Vec::new();
+        while offspring.len() < n {
+            let (parent1, parent2) = (&self.population[pick(rng)], &self.population[pick(rng)]);
+            let (child1, child2) = if rng.gen_bool(self.crossover_chance) {
+                self.crossover.crossover(parent1, parent2, rng)
+            } else {
+                (parent1.clone(), parent2.clone())
+            };
+            offspring.push(child1);
+            offspring.push(child2);
+        }
+        offspring.truncate(n);
+        for child in offspring.iter_mut() {
+            if rng.gen_bool(self.mutation_chance) {
+                self.mutation.mutate(child, rng);
+            }
+        }
+        offspring
+    }
+
+    /// 最後の世代のパレート前線を返す。目的の値が同じ個体は 1 つにまとめる。
+    pub fn run(&mut self) -> Vec<C> {
+        let n = self.population.len();
+        let mut objectives = self
+            .population
+            .iter()
+            .map(C::objectives)
+            .collect::<Vec<_>>();
+        for generation in 0..self.generations {
+            let (_, ranking) = rank_and_crowding(&objectives);
+            let offspring = self.offspring(&ranking);
+            objectives.extend(offspring.iter().map(C::objectives));
+            self.population.extend(offspring);
+            // 前線を順に詰め、入りきらない前線は混雑距離の大きい順に選ぶ
+            let (fronts, ranking) = rank_and_crowding(&objectives);
+            let mut survivors = Vec::new();
+            for mut front in fronts {
+                if survivors.len() + front.len() > n {
+                    front.sort_by_key(|i| std::cmp::Reverse(OrderedFloat(ranking[*i].1)));
+                    front.truncate(n - survivors.len());
+                }
+                survivors.extend(front);
+                if survivors.len() == n {
+                    break;
+                }
+            }
+            self.population = survivors
+                .iter()
+                .map(|i| self.population[*i].clone())
+                .collect();
+            objectives = survivors.iter().map(|i| objectives[*i].clone()).collect();
+            tracing::info!(
+                "Generation {} Front {}",
+                generation,
+                non_dominated_sort(&objectives)[0].len()
+            );
+        }
+        let mut front = Vec::new();
+        let mut seen: Vec<&Vec<f64>> = Vec::new();
+        for i in &non_dominated_sort(&objectives)[0] {
+            if !seen.contains(&&objectives[*i]) {
+                seen.push(&objectives[*i]);
+                front.push(self.population[*i].clone());
+            }
+        }
+        front
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::operators::ArithmeticCrossover;
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn test_non_dominated_sort() {
+        let objectives = vec![
+            vec![1.0, 5.0],
+            vec![2.0, 2.0],
+            vec![3.0, 1.0],
+            vec![1.0, 1.0],
+        ];
+        assert_eq!(
+            non_dominated_sort(&objectives),
+            vec![vec![0, 1, 2], vec![3]]
+        );
+        let distance = crowding_distance(&objectives, &[0, 1, 2]);
+        assert_eq!(distance[0], f64::INFINITY);
+        assert_eq!(distance[2], f64::INFINITY);
+        assert!((distance[1] - (2.0 / 2.0 + 4.0 / 4.0)).abs() < 1e-9);
+    }
+
+    /// Schaffer の問題 f1 = -x^2, f2 = -(x - 2)^2。前線は 0 <= x <= 2。
+    #[derive(Debug, Clone)]
+    struct Schaffer(Vec<f64>);
+
+    impl MultiObjective for Schaffer {
+        fn objectives(&self) -> Vec<f64> {
+            vec![-self.0[0].powi(2), -(self.0[0] - 2.0).powi(2)]
+        }
+    }
+
+    impl super::super::operators::Genome for Schaffer {
+        type Gene = f64;
+
+        fn genes(&self) -> &[f64] {
+            &self.0
+        }
+
+        fn genes_mut(&mut self) -> &mut [f64] {
+            &mut self.0
+        }
+    }
+
+    struct Shift;
+
+    impl Mutation<Schaffer> for Shift {
+        fn mutate(&self, individual: &mut Schaffer, rng: &mut dyn RngCore) {
+            individual.0[0] += rng.gen_range(-0.5..0.5);
+        }
+    }
+
+    #[test]
+    fn test_schaffer() {
+        let mut rng = ChaCha12Rng::seed_from_u64(0);
+        let population = (0..20)
+            .map(|_| Schaffer(vec![rng.gen_range(-10.0..10.0)]))
+            .collect();
+        let front = Nsga2::new(population, 50, 0.3, 0.9, ArithmeticCrossover, Shift)
+            .seed(1)
+            .run();
+        assert!(front.len() > 5);
+        assert!(front.iter().all(|s| (-0.1..=2.1).contains(&s.0[0])));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty_population() {
+        Nsga2::<Schaffer>::new(Vec::new(), 50, 0.3, 0.9, ArithmeticCrossover, Shift);
+    }
+}
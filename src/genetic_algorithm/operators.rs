@@ -0,0 +1,665 @@
+//! 選択、交叉、突然変異、世代交代の戦略
+use ordered_float::OrderedFloat;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::{index::sample, SliceRandom},
+    Rng, RngCore,
+};
+
+use super::Chromosome;
+
+/// 遺伝子の列として扱える染色体。順列や実数ベクトル向けの交叉と突然変異に使う。
+pub trait Genome: Clone {
+    type Gene: Clone + PartialEq;
+    fn genes(&self) -> &[Self::Gene];
+    fn genes_mut(&mut self) -> &mut [Self::Gene];
+}
+
+impl<T: Clone + PartialEq> Genome for Vec<T> {
+    type Gene = T;
+
+    fn genes(&self) -> &[T] {
+        self
+    }
+
+    fn genes_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+/// 適応度から親を count 個選び、その添字を返す。fitness が空なら空を返す。
+pub trait Selection {
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize>;
+}
+
+/// 2 つの親から 2 つの子を作る。
+pub trait Crossover<C> {
+    fn crossover(&self, parent1: &C, parent2: &C, rng: &mut dyn RngCore) -> (C, C);
+}
+
+pub trait Mutation<C> {
+    fn mutate(&self, individual: &mut C, rng: &mut dyn RngCore);
+}
+
+/// 今の世代と子から次の世代を作る。
+pub trait Replacement<C> {
+    /// 1 世代に作る子の数
+    fn offspring_count(&self, population_size: usize) -> usize {
+        population_size
+    }
+
+    fn replace(&self, population: Vec<C>, fitness: &[f64], offspring: Vec<C>) -> Vec<C>;
+}
+
+/// 適応度の高い順に並べた添字
+fn ranking(fitness: &[f64]) -> Vec<usize> {
+    let mut order = (0..fitness.len()).collect::<Vec<_>>();
+    order.sort_by_key(|i| std::cmp::Reverse(OrderedFloat(fitness[*i])));
+    order
+}
+
+/// ルーレットに使う前に、適応度を負にならない重みへ変換する方法
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Scaling {
+    /// 最小の適応度を引く
+    #[default]
+    Offset,
+    /// 平均が 1、最大が c になるように線形に写す。負になった重みは 0 にする。
+    Linear(f64),
+    /// 1 + (f - 平均) / (2 × 標準偏差)。負になった重みは 0 にする。
+    Sigma,
+    /// 順位 (最悪が 1)。同じ適応度は同じ順位になる。
+    Rank,
+}
+
+impl Scaling {
+    /// 重みがすべて 0 になったとき (全員が同じ適応度のときなど) は等しい重みにする。
+    /// 適応度が無限大や f64::MIN などでも、重みは有限で合計があふれないようにする。
+    pub fn weights(&self, fitness: &[f64]) -> Vec<f64> {
+        let n = fitness.len() as f64;
+        let mean = fitness.iter().sum::<f64>() / n;
+        let min = fitness.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = fitness.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let weights = match self {
+            Scaling::Offset => fitness.iter().map(|f| f - min).collect(),
+            Scaling::Linear(c) => fitness
+                .iter()
+                .map(|f| {
+                    if max > mean {
+                        (1.0 + (c - 1.0) * (f - mean) / (max - mean)).max(0.0)
+                    } else {
+                        1.0
+                    }
+                })
+                .collect(),
+            Scaling::Sigma => {
+                let sigma = (fitness.iter().map(|f| (f - mean).powi(2)).sum::<f64>() / n).sqrt();
+                fitness
+                    .iter()
+                    .map(|f| {
+                        if sigma > 0.0 {
+                            (1.0 + (f - mean) / (2.0 * sigma)).max(0.0)
+                        } else {
+                            1.0
+                        }
+                    })
+                    .collect()
+            }
+            Scaling::Rank => {
+                // 同じ適応度には同じ順位を与える
+                let mut weights = vec![0.0; fitness.len()];
+                let mut rank = 0;
+                let order = ranking(fitness).into_iter().rev().collect::<Vec<_>>();
+                for (k, i) in order.iter().enumerate() {
+                    if k == 0 || fitness[order[k - 1]] < fitness[*i] {
+                        rank = k + 1;
+                    }
+                    weights[*i] = rank as f64;
+                }
+                weights
+            }
+        };
+        // 無限大の重みがあればそれだけを等しく選び、NaN は 0 にする
+        let mut weights: Vec<f64> = if weights.contains(&f64::INFINITY) {
+            weights
+                .iter()
+                .map(|w| if *w == f64::INFINITY { 1.0 } else { 0.0 })
+                .collect()
+        } else {
+            weights.iter().map(|w| w.max(0.0)).collect()
+        };
+        if !weights.iter().sum::<f64>().is_finite() {
+            let max = weights.iter().copied().fold(0.0, f64::max);
+            weights.iter_mut().for_each(|w| *w /= max);
+        }
+        if weights.iter().all(|w| *w == 0.0) {
+            vec![1.0; fitness.len()]
+        } else {
+            weights
+        }
+    }
+}
+
+/// 変換した重みに比例した確率で選ぶ。
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Roulette {
+    pub scaling: Scaling,
+}
+
+impl Roulette {
+    pub fn new(scaling: Scaling) -> Self {
+        Self { scaling }
+    }
+}
+
+impl Selection for Roulette {
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        // 重みは有限で負にならないので、失敗するのは fitness が空のときだけ
+        let Ok(wheel) = WeightedIndex::new(self.scaling.weights(fitness)) else {
+            return Vec::new();
+        };
+        (0..count).map(|_| wheel.sample(rng)).collect()
+    }
+}
+
+/// 適応度の順位 (最悪が 1) に比例した確率で選ぶ。同じ適応度は同じ順位になる。
+/// Roulette::new(Scaling::Rank) と同じ。
+pub struct Rank;
+
+impl Selection for Rank {
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        Roulette::new(Scaling::Rank).select(fitness, count, rng)
+    }
+}
+
+/// 確率的普遍抽出 (stochastic universal sampling)。
+/// ルーレットの上に等間隔の針を count 本置いて一度に選ぶので、選ばれる回数のばらつきが小さい。
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StochasticUniversalSampling {
+    pub scaling: Scaling,
+}
+
+impl StochasticUniversalSampling {
+    pub fn new(scaling: Scaling) -> Self {
+        Self { scaling }
+    }
+}
+
+impl Selection for StochasticUniversalSampling {
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        if fitness.is_empty() {
+            return Vec::new();
+        }
+        let fitness = self.scaling.weights(fitness);
+        let total = fitness.iter().sum::<f64>();
+        let step = total / count as f64;
+        let mut pointer = rng.gen::<f64>() * step;
+        let (mut selected, mut i, mut cumulative) = (Vec::new(), 0, fitness[0]);
+        while selected.len() < count {
+            while cumulative <= pointer && i + 1 < fitness.len() {
+                i += 1;
+                cumulative += fitness[i];
+            }
+            selected.push(i);
+            pointer += step;
+        }
+        // 並びのままだと隣り合う親が似るので混ぜる
+        selected.shuffle(rng);
+        selected
+    }
+}
+
+/// size 個を無作為に選び、その中の最良を親にする。
+pub struct Tournament {
+    pub size: usize,
+}
+
+impl Tournament {
+    pub fn new(size: usize) -> Self {
+        Self { size }
+    }
+}
+
+impl Selection for Tournament {
+    fn select(&self, fitness: &[f64], count: usize, rng: &mut dyn RngCore) -> Vec<usize> {
+        if fitness.is_empty() {
+            return Vec::new();
+        }
+        let size = self.size.clamp(1, fitness.len());
+        (0..count)
+            .map(|_| {
+                sample(rng, fitness.len(), size)
+                    .into_iter()
+                    .max_by_key(|i| OrderedFloat(fitness[*i]))
+                    .unwrap()
+            })
+            .collect()
+    }
+}
+
+/// Chromosome::crossover をそのまま使う。
+pub struct ChromosomeCrossover;
+
+impl<C: Chromosome> Crossover<C> for ChromosomeCrossover {
+    fn crossover(&self, parent1: &C, parent2: &C, rng: &mut dyn RngCore) -> (C, C) {
+        parent1.crossover(parent2, rng)
+    }
+}
+
+/// 区間 [start, end) を無作為に選ぶ。
+fn segment(len: usize, rng: &mut dyn RngCore) -> (usize, usize) {
+    let (a, b) = (rng.gen_range(0..=len), rng.gen_range(0..=len));
+    (a.min(b), a.max(b))
+}
+
+/// 順序交叉 (OX)。一方の親の区間を残し、残りをもう一方の親に現れる順に埋める。
+/// OX、PMX、CX は遺伝子がすべて異なる順列を前提にする。
+pub struct OrderCrossover;
+
+impl OrderCrossover {
+    fn child<G: Genome>(keep: &G, order: &G, start: usize, end: usize) -> G {
+        let mut child = keep.clone();
+        let len = keep.genes().len();
+        let kept = &keep.genes()[start..end];
+        let mut rest = (0..len)
+            .map(|k| order.genes()[(end + k) % len].clone())
+            .filter(|gene| !kept.contains(gene))
+            .collect::<Vec<_>>()
+            .into_iter();
+        for k in 0..len - (end - start) {
+            child.genes_mut()[(end + k) % len] = rest.next().unwrap();
+        }
+        child
+    }
+}
+
+impl<G: Genome> Crossover<G> for OrderCrossover {
+    fn crossover(&self, parent1: &G, parent2: &G, rng: &mut dyn RngCore) -> (G, G) {
+        let (start, end) = segment(parent1.genes().len(), rng);
+        (
+            Self::child(parent1, parent2, start, end),
+            Self::child(parent2, parent1, start, end),
+        )
+    }
+}
+
+/// 部分写像交叉 (PMX)。区間の対応をたどって、区間の外の重複を解消する。
+pub struct PartiallyMappedCrossover;
+
+impl PartiallyMappedCrossover {
+    fn child<G: Genome>(keep: &G, other: &G, start: usize, end: usize) -> G {
+        let mut child = other.clone();
+        let (keep, other) = (keep.genes(), other.genes());
+        child.genes_mut()[start..end].clone_from_slice(&keep[start..end]);
+        for k in start..end {
+            if keep[start..end].contains(&other[k]) {
+                continue;
+            }
+            let mut position = k;
+            while (start..end).contains(&position) {
+                position = other.iter().position(|g| *g == keep[position]).unwrap();
+            }
+            child.genes_mut()[position] = other[k].clone();
+        }
+        child
+    }
+}
+
+impl<G: Genome> Crossover<G> for PartiallyMappedCrossover {
+    fn crossover(&self, parent1: &G, parent2: &G, rng: &mut dyn RngCore) -> (G, G) {
+        let (start, end) = segment(parent1.genes().len(), rng);
+        (
+            Self::child(parent1, parent2, start, end),
+            Self::child(parent2, parent1, start, end),
+        )
+    }
+}
+
+/// 循環交叉 (CX)。位置の循環ごとに、どちらの親から受け継ぐかを交互に替える。
+pub struct CycleCrossover;
+
+impl<G: Genome> Crossover<G> for CycleCrossover {
+    fn crossover(&self, parent1: &G, parent2: &G, _rng: &mut dyn RngCore) -> (G, G) {
+        let (mut child1, mut child2) = (parent1.clone(), parent2.clone());
+        let (genes1, genes2) = (parent1.genes(), parent2.genes());
+        let mut visited = vec![false; genes1.len()];
+        let mut swap = false;
+        for start in 0..genes1.len() {
+            if visited[start] {
+                continue;
+            }
+            let mut position = start;
+            while !visited[position] {
+                visited[position] = true;
+                if swap {
+                    child1.genes_mut()[position] = genes2[position].clone();
+                    child2.genes_mut()[position] = genes1[position].clone();
+                }
+                position = genes1.iter().position(|g| *g == genes2[position]).unwrap();
+            }
+            swap = !swap;
+        }
+        (child1, child2)
+    }
+}
+
+/// 一様交叉。遺伝子ごとに半々の確率で入れ替える。
+pub struct UniformCrossover;
+
+impl<G: Genome> Crossover<G> for UniformCrossover {
+    fn crossover(&self, parent1: &G, parent2: &G, rng: &mut dyn RngCore) -> (G, G) {
+        let (mut child1, mut child2) = (parent1.clone(), parent2.clone());
+        for k in 0..parent1.genes().len() {
+            if rng.gen_bool(0.5) {
+                child1.genes_mut()[k] = parent2.genes()[k].clone();
+                child2.genes_mut()[k] = parent1.genes()[k].clone();
+            }
+        }
+        (child1, child2)
+    }
+}
+
+/// 算術交叉。無作為な重み a で a * p1 + (1 - a) * p2 と (1 - a) * p1 + a * p2 を作る。
+pub struct ArithmeticCrossover;
+
+impl<G: Genome<Gene = f64>> Crossover<G> for ArithmeticCrossover {
+    fn crossover(&self, parent1: &G, parent2: &G, rng: &mut dyn RngCore) -> (G, G) {
+        let a = rng.gen::<f64>();
+        let (mut child1, mut child2) = (parent1.clone(), parent2.clone());
+        for (k, (x, y)) in parent1.genes().iter().zip(parent2.genes()).enumerate() {
+            child1.genes_mut()[k] = a * x + (1.0 - a) * y;
+            child2.genes_mut()[k] = (1.0 - a) * x + a * y;
+        }
+        (child1, child2)
+    }
+}
+
+/// Chromosome::mutate をそのまま使う。
+pub struct ChromosomeMutation;
+
+impl<C: Chromosome> Mutation<C> for ChromosomeMutation {
+    fn mutate(&self, individual: &mut C, rng: &mut dyn RngCore) {
+        individual.mutate(rng);
+    }
+}
+
+/// 無作為な 2 つの遺伝子を入れ替える。順列のままになる。
+pub struct SwapMutation;
+
+impl<G: Genome> Mutation<G> for SwapMutation {
+    fn mutate(&self, individual: &mut G, rng: &mut dyn RngCore) {
+        let len = individual.genes().len();
+        if len >= 2 {
+            let indices = sample(rng, len, 2);
+            individual
+                .genes_mut()
+                .swap(indices.index(0), indices.index(1));
+        }
+    }
+}
+
+/// 無作為な区間の並びを逆にする。順列のままで、巡回路なら 2 本の辺だけが変わる。
+pub struct InversionMutation;
+
+impl<G: Genome> Mutation<G> for InversionMutation {
+    fn mutate(&self, individual: &mut G, rng: &mut dyn RngCore) {
+        let (start, end) = segment(individual.genes().len(), rng);
+        individual.genes_mut()[start..end].reverse();
+    }
+}
+
+/// 無作為な 1 つの遺伝子を反転する。
+pub struct FlipMutation;
+
+impl<G: Genome<Gene = bool>> Mutation<G> for FlipMutation {
+    fn mutate(&self, individual: &mut G, rng: &mut dyn RngCore) {
+        let len = individual.genes().len();
+        if len > 0 {
+            let gene = &mut individual.genes_mut()[rng.gen_range(0..len)];
+            *gene = !*gene;
+        }
+    }
+}
+
+/// 子ですべて入れ替える。
+pub struct Generational;
+
+impl<C> Replacement<C> for Generational {
+    fn replace(&self, _population: Vec<C>, _fitness: &[f64], offspring: Vec<C>) -> Vec<C> {
+        offspring
+    }
+}
+
+/// 適応度の高い count 個をそのまま残し、残りを子で入れ替える。
+pub struct Elitism {
+    pub count: usize,
+}
+
+impl Elitism {
+    pub fn new(count: usize) -> Self {
+        Self { count }
+    }
+}
+
+impl<C: Clone> Replacement<C> for Elitism {
+    fn offspring_count(&self, population_size: usize) -> usize {
+        population_size.saturating_sub(self.count)
+    }
+
+    fn replace(&self, population: Vec<C>, fitness: &[f64], offspring: Vec<C>) -> Vec<C> {
+        ranking(fitness)
+            .into_iter()
+            .take(self.count)
+            .map(|i| population[i].clone())
+            .chain(offspring)
+            .collect()
+    }
+}
+
+/// 定常状態。1 世代に count 個だけ子を作り、適応度の低い count 個と入れ替える。
+pub struct SteadyState {
+    pub count: usize,
+}
+
+impl SteadyState {
+    pub fn new(count: usize) -> Self {
+        Self { count }
+    }
+}
+
+impl<C> Replacement<C> for SteadyState {
+    fn offspring_count(&self, population_size: usize) -> usize {
+        self.count.min(population_size)
+    }
+
+    fn replace(&self, mut population: Vec<C>, fitness: &[f64], offspring: Vec<C>) -> Vec<C> {
+        let worst = ranking(fitness).into_iter().rev();
+        for (i, child) in worst.zip(offspring) {
+            population[i] = child;
+        }
+        population
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn is_permutation(genes: &[usize], len: usize) -> bool {
+        let mut sorted = genes.to_vec();
+        sorted.sort();
+        sorted == (0..len).collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn test_permutation_crossovers() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let crossovers: [&dyn Crossover<Vec<usize>>; 3] =
+            [&OrderCrossover, &PartiallyMappedCrossover, &CycleCrossover];
+        for crossover in crossovers {
+            for _ in 0..100 {
+                let mut parent1 = (0..8).collect::<Vec<_>>();
+                let mut parent2 = parent1.clone();
+                parent1.shuffle(&mut rng);
+                parent2.shuffle(&mut rng);
+                let (child1, child2) = crossover.crossover(&parent1, &parent2, &mut rng);
+                assert!(is_permutation(&child1, 8));
+                assert!(is_permutation(&child2, 8));
+                let mut mutated = child1.clone();
+                InversionMutation.mutate(&mut mutated, &mut rng);
+                assert!(is_permutation(&mutated, 8));
+            }
+        }
+    }
+
+    #[test]
+    fn test_cycle_crossover() {
+        let parent1 = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let parent2 = vec![8, 5, 2, 1, 3, 6, 4, 7];
+        let mut rng = StdRng::seed_from_u64(0);
+        let (child1, child2) = CycleCrossover.crossover(&parent1, &parent2, &mut rng);
+        assert_eq!(child1, vec![1, 5, 2, 4, 3, 6, 7, 8]);
+        assert_eq!(child2, vec![8, 2, 3, 1, 5, 6, 4, 7]);
+    }
+
+    #[test]
+    fn test_arithmetic_crossover() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (child1, child2) =
+            ArithmeticCrossover.crossover(&vec![0.0, 2.0], &vec![4.0, 2.0], &mut rng);
+        assert!((child1[0] + child2[0] - 4.0).abs() < 1e-9);
+        assert_eq!((child1[1], child2[1]), (2.0, 2.0));
+    }
+
+    #[test]
+    fn test_selections() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let fitness = [0.0, 1.0, 3.0];
+        let selections: [&dyn Selection; 4] = [
+            &Roulette::default(),
+            &Rank,
+            &StochasticUniversalSampling::default(),
+            &Tournament::new(3),
+        ];
+        for selection in selections {
+            let selected = selection.select(&fitness, 8, &mut rng);
+            assert_eq!(selected.len(), 8);
+            assert!(selected.iter().all(|i| *i < 3));
+        }
+        // 大きさ 3 のトーナメントでは必ず最良が選ばれる
+        assert_eq!(Tournament::new(3).select(&fitness, 4, &mut rng), vec![2; 4]);
+        // 合計 4 に対して 8 本の針なので、適応度 3 はちょうど 6 回選ばれる
+        let selected = StochasticUniversalSampling::default().select(&fitness, 8, &mut rng);
+        assert_eq!(selected.iter().filter(|i| **i == 2).count(), 6);
+        assert!(!selected.contains(&0));
+        assert!(Tournament::new(3).select(&[], 4, &mut rng).is_empty());
+        assert!(StochasticUniversalSampling::default()
+            .select(&[], 4, &mut rng)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_scaling() {
+        let scalings = [
+            Scaling::Offset,
+            Scaling::Linear(2.0),
+            Scaling::Sigma,
+            Scaling::Rank,
+        ];
+        for scaling in scalings {
+            let weights = scaling.weights(&[-109.0, -5000.0, -52.0, -3.0]);
+            assert!(weights.iter().all(|w| *w >= 0.0));
+            // 適応度の順は変わらない
+            assert!(weights[3] > weights[2] && weights[2] > weights[0] && weights[0] >= weights[1]);
+            // 全員同じなら等しい重み
+            assert_eq!(scaling.weights(&[0.0; 3]), vec![1.0; 3]);
+            assert_eq!(scaling.weights(&[-2.0; 3]), vec![1.0; 3]);
+        }
+        assert_eq!(
+            Scaling::Offset.weights(&[-1.0, 1.0, 2.0]),
+            vec![0.0, 2.0, 3.0]
+        );
+        let linear = Scaling::Linear(2.0).weights(&[1.0, 2.0, 3.0]);
+        assert_eq!(linear, vec![0.0, 1.0, 2.0]);
+        assert_eq!(
+            Scaling::Rank.weights(&[5.0, -1.0, 3.0]),
+            vec![3.0, 1.0, 2.0]
+        );
+
+        let mut rng = StdRng::seed_from_u64(0);
+        for fitness in [[-3.0, -2.0, -1.0], [0.0, 0.0, 0.0]] {
+            for scaling in scalings {
+                assert_eq!(
+                    Roulette::new(scaling).select(&fitness, 5, &mut rng).len(),
+                    5
+                );
+                let sus = StochasticUniversalSampling::new(scaling);
+                assert_eq!(sus.select(&fitness, 5, &mut rng).len(), 5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_non_finite_fitness() {
+        let scalings = [
+            Scaling::Offset,
+            Scaling::Linear(2.0),
+            Scaling::Sigma,
+            Scaling::Rank,
+        ];
+        let mut rng = StdRng::seed_from_u64(0);
+        let fitness = [f64::NEG_INFINITY, f64::MIN, 0.0, f64::MAX];
+        for scaling in scalings {
+            let weights = scaling.weights(&fitness);
+            assert!(weights.iter().all(|w| w.is_finite() && *w >= 0.0));
+            assert!(weights.iter().sum::<f64>().is_finite());
+            let selections: [&dyn Selection; 2] = [
+                &Roulette::new(scaling),
+                &StochasticUniversalSampling::new(scaling),
+            ];
+            for selection in selections {
+                let selected = selection.select(&fitness, 8, &mut rng);
+                assert_eq!(selected.len(), 8);
+                assert!(selected.iter().all(|i| *i < fitness.len()));
+            }
+        }
+        // 適応度が負の無限大の個体は選ばれない
+        let selected = Roulette::default().select(&fitness, 100, &mut rng);
+        assert!(!selected.contains(&0));
+        assert!(Roulette::default().select(&[], 4, &mut rng).is_empty());
+        assert!(Rank.select(&[], 4, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn test_rank_ties() {
+        // 同じ適応度は同じ順位なので、Rank と Scaling::Rank は同じ重みで選ぶ
+        let fitness = [1.0, 3.0, 1.0, 2.0, 3.0];
+        let rank = Rank.select(&fitness, 20, &mut StdRng::seed_from_u64(0));
+        let roulette =
+            Roulette::new(Scaling::Rank).select(&fitness, 20, &mut StdRng::seed_from_u64(0));
+        assert_eq!(rank, roulette);
+        assert_eq!(
+            Scaling::Rank.weights(&fitness),
+            vec![1.0, 4.0, 1.0, 3.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn test_replacements() {
+        let population = vec!["a", "b", "c", "d"];
+        let fitness = [2.0, 4.0, 1.0, 3.0];
+        let elitism = Elitism::new(2);
+        assert_eq!(Replacement::<&str>::offspring_count(&elitism, 4), 2);
+        assert_eq!(
+            elitism.replace(population.clone(), &fitness, vec!["x", "y"]),
+            vec!["b", "d", "x", "y"]
+        );
+        let steady_state = SteadyState::new(2);
+        assert_eq!(Replacement::<&str>::offspring_count(&steady_state, 4), 2);
+        assert_eq!(
+            steady_state.replace(population, &fitness, vec!["x", "y"]),
+            vec!["y", "b", "x", "d"]
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! run を止める条件。threshold と max_generations のほかに、いくつでも組み合わせられる。
+use std::fmt;
+use std::time::Duration;
+
+use ordered_float::OrderedFloat;
+
+use super::GenerationStats;
+
+/// run を終えた理由
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Termination {
+    /// 適応度が threshold に届いた
+    Threshold,
+    /// max_generations に達した
+    MaxGenerations,
+    /// 最良の適応度がしばらく上がらなかった
+    Stagnation,
+    /// 経過時間の上限に達した
+    TimeLimit,
+    /// 適応度を計算した回数の上限に達した
+    EvaluationBudget,
+    /// 適応度の標準偏差が下限を割った
+    DiversityCollapse,
+    /// Predicate の名前
+    Predicate(String),
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Termination::Threshold => write!(f, "reached the threshold"),
+            Termination::MaxGenerations => write!(f, "reached the maximum generations"),
+            Termination::Stagnation => write!(f, "stagnated"),
+            Termination::TimeLimit => write!(f, "ran out of time"),
+            Termination::EvaluationBudget => write!(f, "ran out of evaluations"),
+            Termination::DiversityCollapse => write!(f, "lost diversity"),
+            Termination::Predicate(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// 条件を調べるときの run の進み具合
+#[derive(Debug, Clone, Copy)]
+pub struct Progress<'a> {
+    /// 今の世代までの統計。最後が今の世代。
+    pub history: &'a [GenerationStats],
+    /// run を始めてから適応度を計算した回数
+    pub evaluations: usize,
+}
+
+impl Progress<'_> {
+    fn current(&self) -> Option<&GenerationStats> {
+        self.history.last()
+    }
+}
+
+/// 世代を評価するたびに調べ、止めるなら理由を返す。
+pub trait Criterion {
+    fn check(&self, progress: &Progress) -> Option<Termination>;
+}
+
+/// generations 世代のあいだ、それまでの最良の適応度を超えなければ止める。
+pub struct Stagnation {
+    pub generations: usize,
+}
+
+impl Stagnation {
+    pub fn new(generations: usize) -> Self {
+        Self { generations }
+    }
+}
+
+impl Criterion for Stagnation {
+    fn check(&self, progress: &Progress) -> Option<Termination> {
+        let history = progress.history;
+        if history.len() <= self.generations {
+            return None;
+        }
+        let (before, recent) = history.split_at(history.len() - self.generations);
+        let best = |stats: &[GenerationStats]| stats.iter().map(|s| OrderedFloat(s.best)).max();
+        (best(recent) <= best(before)).then_some(Termination::Stagnation)
+    }
+}
+
+/// run を始めてからの時間の上限
+pub struct TimeLimit(pub Duration);
+
+impl Criterion for TimeLimit {
+    fn check(&self, progress: &Progress) -> Option<Termination> {
+        progress
+            .current()
+            .filter(|s| s.elapsed >= self.0)
+            .map(|_| Termination::TimeLimit)
+    }
+}
+
+/// 適応度を計算する回数の上限。世代の途中では止めないので、少し超えることがある。
+pub struct EvaluationBudget(pub usize);
+
+impl Criterion for EvaluationBudget {
+    fn check(&self, progress: &Progress) -> Option<Termination> {
+        (progress.evaluations >= self.0).then_some(Termination::EvaluationBudget)
+    }
+}
+
+/// 適応度の標準偏差が min_diversity を下回ったら止める。
+pub struct DiversityCollapse {
+    pub min_diversity: f64,
+}
+
+impl DiversityCollapse {
+    pub fn new(min_diversity: f64) -> Self {
+        Self { min_diversity }
+    }
+}
+
+impl Criterion for DiversityCollapse {
+    fn check(&self, progress: &Progress) -> Option<Termination> {
+        progress
+            .current()
+            .filter(|s| s.diversity < self.min_diversity)
+            .map(|_| Termination::DiversityCollapse)
+    }
+}
+
+/// 任意の条件。止めたときは name を理由として返す。
+pub struct Predicate<F> {
+    name: String,
+    predicate: F,
+}
+
+impl<F> Predicate<F>
+where
+    F: Fn(&Progress) -> bool,
+{
+    pub fn new(name: &str, predicate: F) -> Self {
+        Self {
+            name: name.to_string(),
+            predicate,
+        }
+    }
+}
+
+impl<F> Criterion for Predicate<F>
+where
+    F: Fn(&Progress) -> bool,
+{
+    fn check(&self, progress: &Progress) -> Option<Termination> {
+        (self.predicate)(progress).then(|| Termination::Predicate(self.name.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(bests: &[f64]) -> Vec<GenerationStats> {
+        bests
+            .iter()
+            .enumerate()
+            .map(|(generation, best)| GenerationStats {
+                generation: generation as u32,
+                best: *best,
+                mean: *best,
+                worst: *best,
+                diversity: *best / 10.0,
+                elapsed: Duration::from_secs(generation as u64),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_criteria() {
+        let history = history(&[1.0, 3.0, 2.0, 3.0]);
+        let progress = Progress {
+            history: &history,
+            evaluations: 40,
+        };
+        assert_eq!(Stagnation::new(3).check(&progress), None);
+        assert_eq!(
+            Stagnation::new(2).check(&progress),
+            Some(Termination::Stagnation)
+        );
+        assert_eq!(TimeLimit(Duration::from_secs(4)).check(&progress), None);
+        assert_eq!(
+            TimeLimit(Duration::from_secs(3)).check(&progress),
+            Some(Termination::TimeLimit)
+        );
+        assert_eq!(
+            EvaluationBudget(40).check(&progress),
+            Some(Termination::EvaluationBudget)
+        );
+        assert_eq!(DiversityCollapse::new(0.3).check(&progress), None);
+        assert_eq!(
+            Predicate::new("enough", |p: &Progress| p.evaluations > 10).check(&progress),
+            Some(Termination::Predicate("enough".to_string()))
+        );
+    }
+}
@@ -124,8 +124,6 @@ impl WeightedEdge {
     }
 }
 
-
-
 pub struct WeightedGraph<Vertex> {
     vertices: Vec<Vertex>,
     edges: Vec<Vec<WeightedEdge>>,
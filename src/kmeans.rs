@@ -15,7 +15,7 @@ fn max(data: &[f64]) -> Option<f64> {
 }
 
 fn mean(data: &[f64]) -> Option<f64> {
-    let sum = data.iter().sum::<f64>() as f64;
+    let sum = data.iter().sum::<f64>();
     let count = data.len();
     match count {
         positive if positive > 0 => Some(sum / count as f64),
@@ -101,7 +101,8 @@ pub struct KMeans {
 }
 
 impl KMeans {
-    pub fn new(k: usize, points: Vec<DataPoint>) -> Self {
+    /// 最初の重心を rng で選ぶ。
+    pub fn new<R: Rng + ?Sized>(k: usize, points: Vec<DataPoint>, rng: &mut R) -> Self {
         if k < 1 {
             panic!("k must be greater than 0");
         }
@@ -111,7 +112,7 @@ impl KMeans {
         };
         kmeans.zscore_normalize();
         for _ in 0..k {
-            let random_point = kmeans.random_point();
+            let random_point = kmeans.random_point(rng);
             kmeans.clusters.push(Cluster {
                 points: Vec::new(),
                 centroid: random_point,
@@ -146,8 +147,7 @@ impl KMeans {
         }
     }
 
-    fn random_point(&self) -> DataPoint {
-        let mut rng = rand::thread_rng();
+    fn random_point<R: Rng + ?Sized>(&self, rng: &mut R) -> DataPoint {
         let mut rand_dimensions = Vec::new();
         for dimension in 0..self.points[0].num_dimensions() {
             let values = self.dimension_slice(dimension);
@@ -200,7 +200,7 @@ impl KMeans {
             self.assign_cluster();
             let old_centroids = self.centroids().into_iter().cloned().collect::<Vec<_>>();
             self.generate_centroids();
-            let old_centroids = old_centroids.iter().map(|x| x).collect::<Vec<_>>();
+            let old_centroids = old_centroids.iter().collect::<Vec<_>>();
             let centroids = self.centroids();
             if old_centroids == centroids {
                 tracing::info!("Converged after {} iterations", iteration);
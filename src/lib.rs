@@ -7,4 +7,9 @@ pub mod graph;
 pub mod kmeans;
 pub mod minimax;
 pub mod neural_network;
+pub mod packing;
+pub mod puzzle;
+pub mod scheduling;
 pub mod tictactoe;
+pub mod tsp;
+pub mod word_search;
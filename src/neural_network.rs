@@ -92,14 +92,9 @@ where
     ) -> Self {
         let mut neurons = Vec::with_capacity(num_neurons);
         for _ in 0..num_neurons {
-            let random_weights = if let Some(previous_layer) = previous_layer.as_ref() {
-                Some(Array::random(
-                    previous_layer.neurons.len(),
-                    Uniform::new(0., 1.),
-                ))
-            } else {
-                None
-            };
+            let random_weights = previous_layer.as_ref().map(|previous_layer| {
+                Array::random(previous_layer.neurons.len(), Uniform::new(0., 1.))
+            });
             neurons.push(Neuron::new(random_weights, learning_rate));
         }
         Self {
@@ -191,7 +186,7 @@ where
             let (front, back) = self.layers.split_at_mut(i);
             let current_layer = &mut front[i - 1];
             let next_layer = &back[0];
-            current_layer.calulate_deltas_for_hidden_layer(&next_layer);
+            current_layer.calulate_deltas_for_hidden_layer(next_layer);
         }
     }
 
@@ -0,0 +1,354 @@
+//! csp で長方形を盤面に重ならないように詰める
+use std::collections::HashMap;
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::csp::{Constraint, CspError, SearchStrategy, CSP};
+
+/// 詰める長方形
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub height: usize,
+    pub width: usize,
+}
+
+impl Rect {
+    pub fn new(height: usize, width: usize) -> Self {
+        Self { height, width }
+    }
+
+    pub fn area(&self) -> usize {
+        self.height * self.width
+    }
+
+    fn rotated(&self) -> Self {
+        Self::new(self.width, self.height)
+    }
+}
+
+/// 長方形の左上の位置と、90 度回したかどうか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub row: usize,
+    pub column: usize,
+    pub rotated: bool,
+}
+
+impl Position {
+    /// 置いたときの (高さ, 幅)
+    fn extent(&self, rect: &Rect) -> Rect {
+        if self.rotated {
+            rect.rotated()
+        } else {
+            *rect
+        }
+    }
+}
+
+/// 2 つの長方形が重ならない
+pub struct NoOverlap {
+    items: (usize, usize),
+    rects: (Rect, Rect),
+}
+
+impl Constraint<usize, Position> for NoOverlap {
+    fn variables(&self) -> Vec<usize> {
+        vec![self.items.0, self.items.1]
+    }
+
+    fn satisfied(&self, assignment: &HashMap<usize, Position>) -> bool {
+        let (Some(first), Some(second)) =
+            (assignment.get(&self.items.0), assignment.get(&self.items.1))
+        else {
+            return true;
+        };
+        let (a, b) = (first.extent(&self.rects.0), second.extent(&self.rects.1));
+        first.row + a.height <= second.row
+            || second.row + b.height <= first.row
+            || first.column + a.width <= second.column
+            || second.column + b.width <= first.column
+    }
+}
+
+/// rows × columns の盤面に items を詰める問題。変数は items の番号。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packing {
+    pub rows: usize,
+    pub columns: usize,
+    pub items: Vec<Rect>,
+    /// 長方形を 90 度回して置いてよいか
+    pub allow_rotation: bool,
+}
+
+impl Packing {
+    pub fn new(rows: usize, columns: usize, items: Vec<Rect>) -> Self {
+        Self {
+            rows,
+            columns,
+            items,
+            allow_rotation: true,
+        }
+    }
+
+    /// 盤面に収まる位置。正方形は回しても同じなので回さない。
+    fn domain(&self, rect: &Rect) -> Vec<Position> {
+        let mut domain = Vec::new();
+        for (row, column) in (0..self.rows).cartesian_product(0..self.columns) {
+            for rotated in [false, true] {
+                if rotated && (!self.allow_rotation || rect.height == rect.width) {
+                    continue;
+                }
+                let position = Position {
+                    row,
+                    column,
+                    rotated,
+                };
+                let extent = position.extent(rect);
+                if row + extent.height <= self.rows && column + extent.width <= self.columns {
+                    domain.push(position);
+                }
+            }
+        }
+        domain
+    }
+
+    /// 大きい長方形から置くように変数を並べる。
+    /// 盤面に収まらない長方形があれば CspError::EmptyDomain になる。
+    pub fn csp(&self) -> Result<CSP<usize, Position, NoOverlap>, CspError<usize>> {
+        let variables = (0..self.items.len())
+            .sorted_by_key(|i| std::cmp::Reverse(self.items[*i].area()))
+            .collect::<Vec<_>>();
+        let domains = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, rect)| (i, self.domain(rect)))
+            .collect();
+        let mut csp = CSP::new(variables, domains)?;
+        for (i, j) in (0..self.items.len()).tuple_combinations() {
+            csp.add_constraint(NoOverlap {
+                items: (i, j),
+                rects: (self.items[i], self.items[j]),
+            })?;
+        }
+        Ok(csp)
+    }
+
+    pub fn solve(&self) -> Result<Option<Layout>, CspError<usize>> {
+        self.solve_with(SearchStrategy::Backjumping)
+    }
+
+    pub fn solve_with(&self, strategy: SearchStrategy) -> Result<Option<Layout>, CspError<usize>> {
+        let (solution, _) = self.csp()?.search(strategy);
+        let Some(solution) = solution else {
+            return Ok(None);
+        };
+        Ok(Some(Layout {
+            rows: self.rows,
+            columns: self.columns,
+            items: (0..self.items.len())
+                .map(|i| (self.items[i], solution[&i]))
+                .collect(),
+        }))
+    }
+
+    /// 幅 columns の帯に items を詰めて、高さが最小になる配置を求める（strip packing）。
+    /// 面積から決まる下限から高さを 1 つずつ増やして解く。
+    pub fn strip_pack(
+        columns: usize,
+        items: Vec<Rect>,
+        allow_rotation: bool,
+    ) -> Result<Option<Layout>, CspError<usize>> {
+        let fits =
+            |rect: &Rect| rect.width <= columns || (allow_rotation && rect.height <= columns);
+        if columns == 0 || !items.iter().all(fits) {
+            return Ok(None);
+        }
+        // 回さずに置けないものは回して、すべて縦に積めば必ず収まる
+        let upper = items
+            .iter()
+            .map(|rect| {
+                if rect.width <= columns {
+                    rect.height
+                } else {
+                    rect.width
+                }
+            })
+            .sum::<usize>();
+        let lower = items
+            .iter()
+            .map(|rect| {
+                if allow_rotation && rect.width <= columns && rect.height <= columns {
+                    rect.height.min(rect.width)
+                } else if rect.width <= columns {
+                    rect.height
+                } else {
+                    rect.width
+                }
+            })
+            .max()
+            .unwrap_or(0)
+            .max(
+                items
+                    .iter()
+                    .map(Rect::area)
+                    .sum::<usize>()
+                    .div_ceil(columns),
+            );
+        for rows in lower..=upper {
+            let layout = Packing {
+                rows,
+                columns,
+                items: items.clone(),
+                allow_rotation,
+            }
+            .solve()?;
+            if layout.is_some() {
+                return Ok(layout);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// 詰めた結果。items は Packing の items と同じ順。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub rows: usize,
+    pub columns: usize,
+    pub items: Vec<(Rect, Position)>,
+}
+
+impl Layout {
+    /// 長方形ごとに 1 から順に振った文字。36 個目以降は `*`。
+    fn label(index: usize) -> char {
+        std::char::from_digit(index as u32 + 1, 36).map_or('*', |c| c.to_ascii_uppercase())
+    }
+
+    /// 長方形を cell_size ピクセル四方のマスで描いた SVG
+    pub fn to_svg(&self, cell_size: usize) -> String {
+        const COLORS: [&str; 8] = [
+            "#e6194b", "#3cb44b", "#ffe119", "#4363d8", "#f58231", "#911eb4", "#46f0f0", "#f032e6",
+        ];
+        let (width, height) = (self.columns * cell_size, self.rows * cell_size);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+            width, height, width, height
+        );
+        svg.push_str(&format!(
+            "  <rect width=\"{}\" height=\"{}\" fill=\"white\" stroke=\"black\"/>\n",
+            width, height
+        ));
+        for (i, (rect, position)) in self.items.iter().enumerate() {
+            let extent = position.extent(rect);
+            svg.push_str(&format!(
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"black\"/>\n",
+                position.column * cell_size,
+                position.row * cell_size,
+                extent.width * cell_size,
+                extent.height * cell_size,
+                COLORS[i % COLORS.len()]
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// 長方形のマスをその番号の文字で、空いたマスを `.` で表す。
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut grid = vec![vec!['.'; self.columns]; self.rows];
+        for (i, (rect, position)) in self.items.iter().enumerate() {
+            let extent = position.extent(rect);
+            for row in &mut grid[position.row..position.row + extent.height] {
+                row[position.column..position.column + extent.width].fill(Self::label(i));
+            }
+        }
+        for row in grid {
+            writeln!(f, "{}", row.into_iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rects(sizes: &[(usize, usize)]) -> Vec<Rect> {
+        sizes.iter().map(|(h, w)| Rect::new(*h, *w)).collect()
+    }
+
+    #[test]
+    fn test_no_overlap() {
+        let constraint = NoOverlap {
+            items: (0, 1),
+            rects: (Rect::new(2, 3), Rect::new(1, 1)),
+        };
+        let at = |row, column, rotated| Position {
+            row,
+            column,
+            rotated,
+        };
+        let mut assignment = HashMap::from([(0, at(0, 0, false))]);
+        assignment.insert(1, at(1, 2, false));
+        assert!(!constraint.satisfied(&assignment));
+        assignment.insert(1, at(0, 3, false));
+        assert!(constraint.satisfied(&assignment));
+        // 回すと 3 × 2 になって重なる
+        assignment.insert(0, at(0, 0, true));
+        assignment.insert(1, at(2, 1, false));
+        assert!(!constraint.satisfied(&assignment));
+    }
+
+    #[test]
+    fn test_solve() {
+        let packing = Packing::new(9, 9, rects(&[(4, 4), (3, 3), (1, 6), (2, 2), (2, 5)]));
+        let layout = packing.solve().unwrap().unwrap();
+        let cells = layout
+            .to_string()
+            .chars()
+            .filter(|c| *c != '.' && *c != '\n')
+            .count();
+        assert_eq!(cells, 16 + 9 + 6 + 4 + 10);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let mut packing = Packing::new(2, 3, rects(&[(3, 1), (1, 3)]));
+        assert!(packing.solve().unwrap().is_some());
+        packing.allow_rotation = false;
+        assert_eq!(packing.solve(), Err(CspError::EmptyDomain(0)));
+        // どちらも収まるが重なる
+        let packing = Packing::new(2, 3, rects(&[(2, 2), (2, 2)]));
+        assert_eq!(packing.solve(), Ok(None));
+    }
+
+    #[test]
+    fn test_strip_pack() {
+        // 2 × 2 が 2 つと 4 × 2 は、幅 4 なら高さ 4 に収まる
+        let layout = Packing::strip_pack(4, rects(&[(2, 2), (2, 2), (4, 2)]), true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(layout.rows, 4);
+        // 回せなければ高さ 4 のまま置く
+        let layout = Packing::strip_pack(4, rects(&[(2, 2), (2, 2), (2, 4)]), false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(layout.rows, 4);
+        assert_eq!(Packing::strip_pack(2, rects(&[(1, 3)]), false), Ok(None));
+    }
+
+    #[test]
+    fn test_render() {
+        let layout = Packing::new(2, 3, rects(&[(2, 2), (1, 1)]))
+            .solve()
+            .unwrap()
+            .unwrap();
+        assert_eq!(layout.to_string().matches('1').count(), 4);
+        assert_eq!(layout.to_string().matches('.').count(), 1);
+        assert_eq!(layout.to_svg(10).matches("<rect").count(), 3);
+    }
+}
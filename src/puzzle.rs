@@ -0,0 +1,215 @@
+//! csp で解くペンシルパズル
+use std::collections::HashMap;
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::csp::Constraint;
+
+pub mod crossword;
+pub mod kakuro;
+pub mod kenken;
+pub mod sudoku;
+
+/// (行, 列)
+pub type Cell = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Operation {
+    fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '+' => Some(Operation::Add),
+            '-' => Some(Operation::Sub),
+            '*' | 'x' | '×' => Some(Operation::Mul),
+            '/' | '÷' => Some(Operation::Div),
+            _ => None,
+        }
+    }
+
+    /// u32 に収まらないときは None を返す。ケージの値より大きいので、満たせないものとして扱える。
+    fn apply(&self, values: &[u32]) -> Option<u32> {
+        match self {
+            Operation::Add => sum(values),
+            Operation::Mul => product(values),
+            // 引き算と割り算は最大値から残りを引く（割る）
+            Operation::Sub => {
+                let max = *values.iter().max()?;
+                let rest = sum(values)? - max;
+                max.checked_sub(rest)
+            }
+            Operation::Div => {
+                let max = *values.iter().max()?;
+                let rest = product(values)? / max;
+                if rest != 0 && max % rest == 0 {
+                    Some(max / rest)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+fn sum(values: &[u32]) -> Option<u32> {
+    values
+        .iter()
+        .try_fold(0u32, |sum, value| sum.checked_add(*value))
+}
+
+fn product(values: &[u32]) -> Option<u32> {
+    values
+        .iter()
+        .try_fold(1u32, |product, value| product.checked_mul(*value))
+}
+
+/// パズルに共通の制約
+pub enum PuzzleConstraint {
+    /// マスの値がすべて異なる
+    AllDifferent(Vec<Cell>),
+    /// マスの値に operation を適用すると target になる
+    Cage {
+        cells: Vec<Cell>,
+        operation: Operation,
+        target: u32,
+    },
+}
+
+impl Constraint<Cell, u32> for PuzzleConstraint {
+    fn variables(&self) -> Vec<Cell> {
+        match self {
+            PuzzleConstraint::AllDifferent(cells) => cells.clone(),
+            PuzzleConstraint::Cage { cells, .. } => cells.clone(),
+        }
+    }
+
+    fn satisfied(&self, assignment: &HashMap<Cell, u32>) -> bool {
+        match self {
+            PuzzleConstraint::AllDifferent(cells) => {
+                cells.iter().filter_map(|c| assignment.get(c)).all_unique()
+            }
+            PuzzleConstraint::Cage {
+                cells,
+                operation,
+                target,
+            } => {
+                let values = cells
+                    .iter()
+                    .filter_map(|c| assignment.get(c).copied())
+                    .collect::<Vec<_>>();
+                let remaining = (cells.len() - values.len()) as u32;
+                if remaining == 0 {
+                    return operation.apply(&values) == Some(*target);
+                }
+                // 途中の割り当てでも明らかに満たせないものは枝刈りする
+                match operation {
+                    Operation::Add => sum(&values)
+                        .and_then(|sum| sum.checked_add(remaining))
+                        .is_some_and(|sum| sum <= *target),
+                    Operation::Mul => product(&values).is_some_and(|product| target % product == 0),
+                    Operation::Sub | Operation::Div => true,
+                }
+            }
+        }
+    }
+}
+
+/// パズルのテキスト表現を読み込むときのエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePuzzleError {
+    /// 行や盤面の長さが不正
+    InvalidLength(usize),
+    /// 解釈できない文字
+    InvalidCharacter(char),
+    /// 解釈できない語句
+    InvalidToken(String),
+    /// 盤面に現れるのに定義がないケージ
+    MissingCage(char),
+    /// 盤面に現れないケージの定義
+    UnknownCage(char),
+    /// 対応するマスがないヒント
+    EmptyRun(Cell),
+}
+
+impl fmt::Display for ParsePuzzleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsePuzzleError::InvalidLength(len) => write!(f, "invalid length {}", len),
+            ParsePuzzleError::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+            ParsePuzzleError::InvalidToken(token) => write!(f, "invalid token {:?}", token),
+            ParsePuzzleError::MissingCage(id) => write!(f, "cage {:?} is not defined", id),
+            ParsePuzzleError::UnknownCage(id) => write!(f, "cage {:?} is not on the grid", id),
+            ParsePuzzleError::EmptyRun((row, column)) => {
+                write!(f, "clue at ({}, {}) has no cells", row, column)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParsePuzzleError {}
+
+/// 1..=max_value の異なる len 個の値の和が total になるとき、各値が取り得る候補
+fn distinct_sum_domain(max_value: u32, len: usize, total: u32) -> Vec<u32> {
+    if len == 0 || len > max_value as usize {
+        return Vec::new();
+    }
+    let others = len as u32 - 1;
+    let min_others = others * (others + 1) / 2;
+    let max_others = (max_value + 1 - others..=max_value).sum::<u32>();
+    (1..=max_value)
+        .filter(|v| v + min_others <= total && total <= v + max_others)
+        .collect()
+}
+
+/// ケージのマスと、定義の文字列
+type CageDefinitions = Vec<(Vec<Cell>, String)>;
+
+/// `aabb` のようなケージ ID の盤面と、`a 3+` のような定義の行を読み込む。
+/// 盤面は正方形でなければならない。
+fn parse_cages(s: &str) -> Result<(usize, CageDefinitions), ParsePuzzleError> {
+    let mut lines = s.lines().map(str::trim).filter(|l| !l.is_empty());
+    let first = lines.next().ok_or(ParsePuzzleError::InvalidLength(0))?;
+    let size = first.chars().count();
+    let mut grid = vec![first];
+    for _ in 1..size {
+        grid.push(
+            lines
+                .next()
+                .ok_or(ParsePuzzleError::InvalidLength(grid.len()))?,
+        );
+    }
+
+    let mut cells: HashMap<char, Vec<Cell>> = HashMap::new();
+    for (row, line) in grid.iter().enumerate() {
+        if line.chars().count() != size {
+            return Err(ParsePuzzleError::InvalidLength(line.chars().count()));
+        }
+        for (column, id) in line.chars().enumerate() {
+            cells.entry(id).or_default().push((row, column));
+        }
+    }
+
+    let mut cages = Vec::new();
+    for line in lines {
+        let (id, definition) = line
+            .split_whitespace()
+            .collect_tuple()
+            .ok_or_else(|| ParsePuzzleError::InvalidToken(line.to_string()))?;
+        let id = id
+            .chars()
+            .exactly_one()
+            .map_err(|_| ParsePuzzleError::InvalidToken(id.to_string()))?;
+        let cage = cells.remove(&id).ok_or(ParsePuzzleError::UnknownCage(id))?;
+        cages.push((cage, definition.to_string()));
+    }
+    if let Some(id) = cells.keys().min() {
+        return Err(ParsePuzzleError::MissingCage(*id));
+    }
+    Ok((size, cages))
+}
@@ -0,0 +1,445 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use itertools::Itertools;
+
+use super::{Cell, ParsePuzzleError};
+use crate::csp::{Constraint, CspError, SearchStrategy, CSP};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Square {
+    Black,
+    Empty,
+    Letter(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    Across,
+    Down,
+}
+
+/// 2 マス以上続く白マスの並び。1 つの単語が入る。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Slot {
+    pub row: usize,
+    pub column: usize,
+    pub orientation: Orientation,
+    pub len: usize,
+}
+
+impl Slot {
+    pub fn cells(&self) -> Vec<Cell> {
+        (0..self.len)
+            .map(|i| match self.orientation {
+                Orientation::Across => (self.row, self.column + i),
+                Orientation::Down => (self.row + i, self.column),
+            })
+            .collect()
+    }
+}
+
+/// 交わるスロットの番号の組と、それぞれの単語の中で交わる位置
+type Crossing = ((usize, usize), (usize, usize));
+
+/// スロットの番号を変数、単語を値とする制約
+pub enum CrosswordConstraint {
+    /// 2 つのスロットが交わるマスでは同じ文字になる。positions はそれぞれの単語の中の位置。
+    Crossing {
+        slots: (usize, usize),
+        positions: (usize, usize),
+    },
+    /// 同じ単語を 2 回使わない
+    Distinct(usize, usize),
+}
+
+impl Constraint<usize, String> for CrosswordConstraint {
+    fn variables(&self) -> Vec<usize> {
+        match self {
+            CrosswordConstraint::Crossing { slots, .. } => vec![slots.0, slots.1],
+            CrosswordConstraint::Distinct(first, second) => vec![*first, *second],
+        }
+    }
+
+    fn satisfied(&self, assignment: &HashMap<usize, String>) -> bool {
+        match self {
+            CrosswordConstraint::Crossing { slots, positions } => {
+                match (assignment.get(&slots.0), assignment.get(&slots.1)) {
+                    (Some(first), Some(second)) => {
+                        first.as_bytes()[positions.0] == second.as_bytes()[positions.1]
+                    }
+                    _ => true,
+                }
+            }
+            CrosswordConstraint::Distinct(first, second) => {
+                match (assignment.get(first), assignment.get(second)) {
+                    (Some(first), Some(second)) => first != second,
+                    _ => true,
+                }
+            }
+        }
+    }
+}
+
+/// クロスワードの盤面。`#` が黒マス、`.` が空きマス、英字はあらかじめ入っている文字。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crossword {
+    squares: Vec<Vec<Square>>,
+}
+
+impl Crossword {
+    pub fn rows(&self) -> usize {
+        self.squares.len()
+    }
+
+    pub fn columns(&self) -> usize {
+        self.squares.first().map_or(0, Vec::len)
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> Square {
+        self.squares[row][column]
+    }
+
+    /// 1 行に 1 単語の辞書を読み込む。英字だけの単語を大文字にして、重複を除く。
+    pub fn parse_dictionary(s: &str) -> Vec<String> {
+        s.lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty() && word.bytes().all(|b| b.is_ascii_alphabetic()))
+            .map(str::to_ascii_uppercase)
+            .unique()
+            .collect()
+    }
+
+    /// 横、縦の順に、左上から並べたスロット
+    pub fn slots(&self) -> Vec<Slot> {
+        let mut slots = Vec::new();
+        for orientation in [Orientation::Across, Orientation::Down] {
+            let (outer, inner) = match orientation {
+                Orientation::Across => (self.rows(), self.columns()),
+                Orientation::Down => (self.columns(), self.rows()),
+            };
+            for i in 0..outer {
+                let position = |j: usize| match orientation {
+                    Orientation::Across => (i, j),
+                    Orientation::Down => (j, i),
+                };
+                // 黒マスで区切られた白マスの並び
+                let mut start = 0;
+                for j in 0..=inner {
+                    let black = j == inner || {
+                        let (row, column) = position(j);
+                        self.squares[row][column] == Square::Black
+                    };
+                    if black {
+                        if j - start >= 2 {
+                            let (row, column) = position(start);
+                            slots.push(Slot {
+                                row,
+                                column,
+                                orientation,
+                                len: j - start,
+                            });
+                        }
+                        start = j + 1;
+                    }
+                }
+            }
+        }
+        slots
+    }
+
+    fn crossings(slots: &[Slot]) -> Vec<Crossing> {
+        let mut owners: HashMap<Cell, Vec<(usize, usize)>> = HashMap::new();
+        for (i, slot) in slots.iter().enumerate() {
+            for (position, cell) in slot.cells().into_iter().enumerate() {
+                owners.entry(cell).or_default().push((i, position));
+            }
+        }
+        owners
+            .into_values()
+            .filter(|owners| owners.len() == 2)
+            .map(|owners| ((owners[0].0, owners[1].0), (owners[0].1, owners[1].1)))
+            .sorted()
+            .collect()
+    }
+
+    /// スロットに入り得る単語。長さと、あらかじめ入っている文字が合うものに限る。
+    fn candidates(&self, slot: &Slot, dictionary: &[String]) -> Vec<String> {
+        let cells = slot.cells();
+        dictionary
+            .iter()
+            .filter(|word| word.len() == slot.len)
+            .filter(|word| {
+                cells
+                    .iter()
+                    .zip(word.bytes())
+                    .all(
+                        |((row, column), letter)| match self.squares[*row][*column] {
+                            Square::Letter(fixed) => fixed == letter,
+                            _ => true,
+                        },
+                    )
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// 交わるマスで入り得る文字がないものを、変化がなくなるまで取り除く（AC-3）。
+    fn propagate(domains: &mut [Vec<String>], crossings: &[Crossing]) {
+        // (絞り込むスロット, その中の位置, 相手のスロット, 相手の中の位置)
+        let arcs = crossings
+            .iter()
+            .flat_map(|((a, b), (i, j))| [(*a, *i, *b, *j), (*b, *j, *a, *i)])
+            .collect::<Vec<_>>();
+        let mut queue = arcs.iter().copied().collect::<VecDeque<_>>();
+        while let Some((slot, position, other, other_position)) = queue.pop_front() {
+            let letters = domains[other]
+                .iter()
+                .map(|word| word.as_bytes()[other_position])
+                .collect::<HashSet<_>>();
+            let before = domains[slot].len();
+            domains[slot].retain(|word| letters.contains(&word.as_bytes()[position]));
+            if domains[slot].len() < before {
+                queue.extend(
+                    arcs.iter()
+                        .filter(|(_, _, other, _)| *other == slot)
+                        .copied(),
+                );
+            }
+        }
+    }
+
+    /// スロットを変数とする CSP。辞書の単語は交わるマスで文字が合うものにあらかじめ絞り込み、
+    /// すでに置いたスロットとよく交わるスロットから順に割り当てるように変数を並べる。
+    /// 単語が 1 つも入らないスロットがあれば CspError::EmptyDomain になる。
+    pub fn csp(
+        &self,
+        dictionary: &[String],
+    ) -> Result<CSP<usize, String, CrosswordConstraint>, CspError<usize>> {
+        let slots = self.slots();
+        let crossings = Self::crossings(&slots);
+        let mut domains = slots
+            .iter()
+            .map(|slot| self.candidates(slot, dictionary))
+            .collect::<Vec<_>>();
+        Self::propagate(&mut domains, &crossings);
+
+        let mut order: Vec<usize> = Vec::new();
+        while order.len() < slots.len() {
+            let next = (0..slots.len())
+                .filter(|i| !order.contains(i))
+                .max_by_key(|i| {
+                    let placed = crossings
+                        .iter()
+                        .filter(|((a, b), _)| {
+                            (a == i && order.contains(b)) || (b == i && order.contains(a))
+                        })
+                        .count();
+                    (placed, slots[*i].len, std::cmp::Reverse(domains[*i].len()))
+                })
+                .unwrap();
+            order.push(next);
+        }
+
+        let domains = domains.into_iter().enumerate().collect();
+        let mut csp = CSP::new(order, domains)?;
+        for (slots, positions) in crossings {
+            csp.add_constraint(CrosswordConstraint::Crossing { slots, positions })?;
+        }
+        for ((i, first), (j, second)) in slots.iter().enumerate().tuple_combinations() {
+            if first.len == second.len {
+                csp.add_constraint(CrosswordConstraint::Distinct(i, j))?;
+            }
+        }
+        Ok(csp)
+    }
+
+    /// 辞書の単語で盤面を埋める。
+    /// 入る単語のなくなったスロットがあれば CspError::EmptyDomain を返す。
+    pub fn fill(&self, dictionary: &[String]) -> Result<Option<Crossword>, CspError<usize>> {
+        let (solution, _) = self.csp(dictionary)?.search(SearchStrategy::Backjumping);
+        let Some(solution) = solution else {
+            return Ok(None);
+        };
+        let mut filled = self.clone();
+        for (i, slot) in self.slots().iter().enumerate() {
+            for ((row, column), letter) in slot.cells().into_iter().zip(solution[&i].bytes()) {
+                filled.squares[row][column] = Square::Letter(letter);
+            }
+        }
+        Ok(Some(filled))
+    }
+}
+
+impl FromStr for Crossword {
+    type Err = ParsePuzzleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let squares = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.chars()
+                    .map(|c| match c {
+                        '#' => Ok(Square::Black),
+                        '.' => Ok(Square::Empty),
+                        _ if c.is_ascii_alphabetic() => {
+                            Ok(Square::Letter(c.to_ascii_uppercase() as u8))
+                        }
+                        _ => Err(ParsePuzzleError::InvalidCharacter(c)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let columns = squares.first().map_or(0, Vec::len);
+        if let Some(row) = squares.iter().find(|row| row.len() != columns) {
+            return Err(ParsePuzzleError::InvalidLength(row.len()));
+        }
+        Ok(Self { squares })
+    }
+}
+
+impl fmt::Display for Crossword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.squares {
+            let line = row
+                .iter()
+                .map(|square| match square {
+                    Square::Black => '#',
+                    Square::Empty => '.',
+                    Square::Letter(letter) => *letter as char,
+                })
+                .collect::<String>();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DICTIONARY: &str = "cat\ncar\nare\nate\ntea\near\nart\nrat\ntar\nten\ntot\nand";
+
+    #[test]
+    fn test_slots() {
+        let crossword = Crossword::from_str(
+            "...#
+             .#..
+             ....",
+        )
+        .unwrap();
+        let slots = crossword.slots();
+        let across = |row, column, len| Slot {
+            row,
+            column,
+            orientation: Orientation::Across,
+            len,
+        };
+        let down = |row, column, len| Slot {
+            row,
+            column,
+            orientation: Orientation::Down,
+            len,
+        };
+        assert_eq!(
+            slots,
+            [
+                across(0, 0, 3),
+                across(1, 2, 2),
+                across(2, 0, 4),
+                down(0, 0, 3),
+                down(0, 2, 3),
+                down(1, 3, 2),
+            ]
+        );
+        assert_eq!(
+            Crossword::crossings(&slots),
+            [
+                ((0, 3), (0, 0)),
+                ((0, 4), (2, 0)),
+                ((1, 4), (0, 1)),
+                ((1, 5), (1, 0)),
+                ((2, 3), (0, 2)),
+                ((2, 4), (2, 2)),
+                ((2, 5), (3, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill() {
+        let dictionary = Crossword::parse_dictionary(DICTIONARY);
+        let crossword = Crossword::from_str(
+            "C..
+             .#.
+             ...",
+        )
+        .unwrap();
+        let filled = crossword.fill(&dictionary).unwrap().unwrap();
+        let words = words(&filled);
+        assert_eq!(filled.get(0, 0), Square::Letter(b'C'));
+        assert!(words.iter().all(|word| dictionary.contains(word)));
+        assert!(words.iter().all_unique());
+    }
+
+    #[test]
+    fn test_fill_grid7() {
+        let dictionary = Crossword::parse_dictionary(include_str!("../../crossword/words.txt"));
+        let crossword = Crossword::from_str(include_str!("../../crossword/grid7.txt")).unwrap();
+        let filled = crossword.fill(&dictionary).unwrap().unwrap();
+        let words = words(&filled);
+        assert_eq!(words.len(), crossword.slots().len());
+        assert!(words.iter().all(|word| dictionary.contains(word)));
+        assert!(words.iter().all_unique());
+    }
+
+    /// 埋まった盤面の各スロットの単語
+    fn words(filled: &Crossword) -> Vec<String> {
+        filled
+            .slots()
+            .iter()
+            .map(|slot| {
+                slot.cells()
+                    .into_iter()
+                    .map(|(row, column)| match filled.get(row, column) {
+                        Square::Letter(letter) => letter as char,
+                        _ => '?',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_no_fill() {
+        let dictionary = Crossword::parse_dictionary(DICTIONARY);
+        let crossword = Crossword::from_str("Q..\n...\n...").unwrap();
+        assert!(matches!(
+            crossword.csp(&dictionary),
+            Err(CspError::EmptyDomain(_))
+        ));
+        assert!(matches!(
+            crossword.fill(&dictionary),
+            Err(CspError::EmptyDomain(_))
+        ));
+        // どのスロットにも候補はあるが、交差で矛盾する
+        let crossword = Crossword::from_str("...\n...\n...").unwrap();
+        assert_eq!(crossword.fill(&dictionary), Ok(None));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            Crossword::from_str("..#\n.?."),
+            Err(ParsePuzzleError::InvalidCharacter('?'))
+        );
+        assert_eq!(
+            Crossword::from_str("..#\n.."),
+            Err(ParsePuzzleError::InvalidLength(2))
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use itertools::Itertools;
+
+use super::{distinct_sum_domain, Cell, Operation, ParsePuzzleError, PuzzleConstraint};
+use crate::csp::{CspError, CSP};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KakuroCell {
+    Block,
+    White,
+    /// 下方向と右方向の和のヒント
+    Clue {
+        down: Option<u32>,
+        across: Option<u32>,
+    },
+}
+
+/// カックロ。ヒントから続く白マスの並びには 1..=9 の異なる値が入り、和がヒントの値になる。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kakuro {
+    grid: Vec<Vec<KakuroCell>>,
+    runs: Vec<(Vec<Cell>, u32)>,
+}
+
+impl Kakuro {
+    pub fn new(grid: Vec<Vec<KakuroCell>>) -> Result<Self, ParsePuzzleError> {
+        let is_white = |row: usize, column: usize| {
+            grid.get(row).and_then(|r| r.get(column)) == Some(&KakuroCell::White)
+        };
+        let mut runs = Vec::new();
+        for (row, cells) in grid.iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                if let KakuroCell::Clue { down, across } = cell {
+                    if let Some(total) = across {
+                        let run = (column + 1..)
+                            .take_while(|c| is_white(row, *c))
+                            .map(|c| (row, c))
+                            .collect::<Vec<_>>();
+                        if run.is_empty() {
+                            return Err(ParsePuzzleError::EmptyRun((row, column)));
+                        }
+                        runs.push((run, *total));
+                    }
+                    if let Some(total) = down {
+                        let run = (row + 1..)
+                            .take_while(|r| is_white(*r, column))
+                            .map(|r| (r, column))
+                            .collect::<Vec<_>>();
+                        if run.is_empty() {
+                            return Err(ParsePuzzleError::EmptyRun((row, column)));
+                        }
+                        runs.push((run, *total));
+                    }
+                }
+            }
+        }
+        Ok(Self { grid, runs })
+    }
+
+    pub fn runs(&self) -> &[(Vec<Cell>, u32)] {
+        &self.runs
+    }
+
+    /// 白マスのドメインは、そのマスを含む並びの和から取り得る値に絞る。
+    pub fn csp(&self) -> Result<CSP<Cell, u32, PuzzleConstraint>, CspError<Cell>> {
+        let mut domains: HashMap<Cell, Vec<u32>> = HashMap::new();
+        for (row, cells) in self.grid.iter().enumerate() {
+            for (column, cell) in cells.iter().enumerate() {
+                if *cell == KakuroCell::White {
+                    domains.insert((row, column), (1..=9).collect());
+                }
+            }
+        }
+        for (cells, total) in &self.runs {
+            let candidates = distinct_sum_domain(9, cells.len(), *total);
+            for cell in cells {
+                domains
+                    .get_mut(cell)
+                    .unwrap()
+                    .retain(|v| candidates.contains(v));
+            }
+        }
+        // 短い並びから順に探索する
+        let variables = self
+            .runs
+            .iter()
+            .sorted_by_key(|(cells, _)| cells.len())
+            .flat_map(|(cells, _)| cells)
+            .chain(domains.keys().sorted())
+            .unique()
+            .copied()
+            .collect();
+
+        let mut csp = CSP::new(variables, domains)?;
+        for (cells, total) in &self.runs {
+            csp.add_constraint(PuzzleConstraint::AllDifferent(cells.clone()))?;
+            csp.add_constraint(PuzzleConstraint::Cage {
+                cells: cells.clone(),
+                operation: Operation::Add,
+                target: *total,
+            })?;
+        }
+        Ok(csp)
+    }
+
+    /// 並びのマスの数では作れない和があれば CspError::EmptyDomain を返す。
+    pub fn solve(&self) -> Result<Option<HashMap<Cell, u32>>, CspError<Cell>> {
+        Ok(self.csp()?.backtracking_search())
+    }
+
+    /// 白マスに解の値を、それ以外に `#` を置いた盤面
+    pub fn render(&self, solution: &HashMap<Cell, u32>) -> String {
+        self.grid
+            .iter()
+            .enumerate()
+            .map(|(row, cells)| {
+                (0..cells.len())
+                    .map(|column| match solution.get(&(row, column)) {
+                        Some(value) => value.to_string(),
+                        None => "#".to_string(),
+                    })
+                    .join(" ")
+            })
+            .join("\n")
+    }
+}
+
+/// 空白区切りの盤面を読み込む。
+/// `#` は黒マス、`.` は白マス、`下\右` はヒント（`\16` や `23\` のように片方は省略できる）。
+impl FromStr for Kakuro {
+    type Err = ParsePuzzleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_total = |total: &str| match total {
+            "" => Ok(None),
+            _ => total
+                .parse()
+                .map(Some)
+                .map_err(|_| ParsePuzzleError::InvalidToken(total.to_string())),
+        };
+        let grid = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|token| match token {
+                        "#" => Ok(KakuroCell::Block),
+                        "." => Ok(KakuroCell::White),
+                        _ => match token.split_once('\\') {
+                            Some((down, across)) => Ok(KakuroCell::Clue {
+                                down: parse_total(down)?,
+                                across: parse_total(across)?,
+                            }),
+                            None => Err(ParsePuzzleError::InvalidToken(token.to_string())),
+                        },
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let columns = grid.first().map_or(0, Vec::len);
+        if let Some(row) = grid.iter().find(|row| row.len() != columns) {
+            return Err(ParsePuzzleError::InvalidLength(row.len()));
+        }
+        Kakuro::new(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kakuro() {
+        let kakuro = Kakuro::from_str(
+            r"#   4\ 10\
+              \3  .  .
+              \11 .  .",
+        )
+        .unwrap();
+        assert_eq!(kakuro.runs().len(), 4);
+        let solution = kakuro.solve().unwrap().unwrap();
+        assert_eq!(kakuro.render(&solution), "# # #\n# 1 2\n# 3 8");
+    }
+
+    #[test]
+    fn test_empty_run() {
+        assert_eq!(
+            Kakuro::from_str(r"# \3"),
+            Err(ParsePuzzleError::EmptyRun((0, 1)))
+        );
+    }
+}
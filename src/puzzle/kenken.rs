@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use itertools::Itertools;
+
+use super::{parse_cages, Cell, Operation, ParsePuzzleError, PuzzleConstraint};
+use crate::csp::{CspError, CSP};
+
+/// KenKen。各行・各列に 1..=size が 1 回ずつ入り、
+/// ケージの値に演算を適用すると指定の値になる。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KenKen {
+    size: usize,
+    cages: Vec<(Vec<Cell>, Operation, u32)>,
+}
+
+impl KenKen {
+    pub fn new(size: usize, cages: Vec<(Vec<Cell>, Operation, u32)>) -> Self {
+        Self { size, cages }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// ケージごとにまとめて、小さいケージから探索されるように変数を並べる。
+    pub fn csp(&self) -> Result<CSP<Cell, u32, PuzzleConstraint>, CspError<Cell>> {
+        let mut variables = Vec::new();
+        let mut domains = HashMap::new();
+        for (cells, _, target) in self.cages.iter().sorted_by_key(|(cells, ..)| cells.len()) {
+            for cell in cells {
+                variables.push(*cell);
+                // 1 マスのケージは値が決まっている。盤面に入らない値なら解はない。
+                let domain = (1..=self.size as u32)
+                    .filter(|value| cells.len() > 1 || value == target)
+                    .collect();
+                domains.insert(*cell, domain);
+            }
+        }
+
+        let mut csp = CSP::new(variables, domains)?;
+        for i in 0..self.size {
+            csp.add_constraint(PuzzleConstraint::AllDifferent(
+                (0..self.size).map(|column| (i, column)).collect(),
+            ))?;
+            csp.add_constraint(PuzzleConstraint::AllDifferent(
+                (0..self.size).map(|row| (row, i)).collect(),
+            ))?;
+        }
+        for (cells, operation, target) in &self.cages {
+            csp.add_constraint(PuzzleConstraint::Cage {
+                cells: cells.clone(),
+                operation: *operation,
+                target: *target,
+            })?;
+        }
+        Ok(csp)
+    }
+
+    /// 盤面に入らない値の 1 マスのケージがあれば CspError::EmptyDomain を返す。
+    pub fn solve(&self) -> Result<Option<Vec<Vec<u32>>>, CspError<Cell>> {
+        let solution = self.csp()?.backtracking_search();
+        Ok(solution.map(|solution| {
+            let mut grid = vec![vec![0; self.size]; self.size];
+            for ((row, column), value) in solution {
+                grid[row][column] = value;
+            }
+            grid
+        }))
+    }
+}
+
+/// ケージ ID の盤面に続けて、`a 12*` のようにケージの値と演算を並べた表記を読み込む。
+/// 1 マスのケージは `b 3` のように演算を省略する。
+impl FromStr for KenKen {
+    type Err = ParsePuzzleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (size, cages) = parse_cages(s)?;
+        let cages = cages
+            .into_iter()
+            .map(|(cells, definition)| {
+                let invalid = || ParsePuzzleError::InvalidToken(definition.clone());
+                let last = definition.chars().last().ok_or_else(invalid)?;
+                let (target, operation) = match Operation::from_symbol(last) {
+                    Some(operation) => {
+                        (&definition[..definition.len() - last.len_utf8()], operation)
+                    }
+                    None if cells.len() == 1 => (definition.as_str(), Operation::Add),
+                    None => return Err(invalid()),
+                };
+                let target = target.parse().map_err(|_| invalid())?;
+                Ok((cells, operation, target))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { size, cages })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csp::Constraint;
+
+    #[test]
+    fn test_kenken() {
+        let kenken = KenKen::from_str(
+            "aabc
+             dbbc
+             deff
+             ggf.
+             a 2/
+             b 8+
+             c 2-
+             d 7+
+             e 1
+             f 24*
+             g 1-
+             . 1",
+        )
+        .unwrap();
+        assert_eq!(kenken.size(), 4);
+        assert_eq!(kenken.csp().unwrap().count_solutions(2), 1);
+        let solution = kenken.solve().unwrap().unwrap();
+        assert_eq!(
+            solution,
+            vec![
+                vec![1, 2, 3, 4],
+                vec![3, 4, 1, 2],
+                vec![4, 1, 2, 3],
+                vec![2, 3, 4, 1],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_cage() {
+        assert_eq!(
+            KenKen::from_str("ab\nba\na 3+\nb 3"),
+            Err(ParsePuzzleError::InvalidToken("3".to_string()))
+        );
+        assert_eq!(
+            KenKen::from_str("ab\nba\na 3+"),
+            Err(ParsePuzzleError::MissingCage('b'))
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_target() {
+        // 4 × 4 の盤面に 7 は入らない
+        let kenken = KenKen::from_str(
+            "aabc
+             dbbc
+             deff
+             ggf.
+             a 2/
+             b 8+
+             c 2-
+             d 7+
+             e 7
+             f 24*
+             g 1-
+             . 1",
+        )
+        .unwrap();
+        assert_eq!(kenken.solve(), Err(CspError::EmptyDomain((2, 1))));
+    }
+
+    #[test]
+    fn test_large_product() {
+        // 9 の 11 乗は u32 に収まらない
+        assert_eq!(Operation::Mul.apply(&[9; 11]), None);
+        assert_eq!(Operation::Div.apply(&[9; 11]), None);
+        let cells = (0..12).map(|column| (0, column)).collect::<Vec<_>>();
+        let cage = PuzzleConstraint::Cage {
+            cells: cells.clone(),
+            operation: Operation::Mul,
+            target: 362880,
+        };
+        let assignment = cells[..11].iter().map(|cell| (*cell, 9)).collect();
+        assert!(!cage.satisfied(&assignment));
+    }
+}
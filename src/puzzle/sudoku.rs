@@ -0,0 +1,406 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng};
+
+use super::{
+    distinct_sum_domain, parse_cages, Cell, Operation, ParsePuzzleError, PuzzleConstraint,
+};
+use crate::csp::{CspError, CSP};
+
+/// 一辺が order² の数独。order は箱の一辺の長さで、普通の 9x9 なら 3。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sudoku {
+    order: usize,
+    cells: Vec<Option<u32>>,
+}
+
+impl Sudoku {
+    pub fn new(order: usize) -> Self {
+        let size = order * order;
+        Self {
+            order,
+            cells: vec![None; size * size],
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    pub fn size(&self) -> usize {
+        self.order * self.order
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> Option<u32> {
+        self.cells[row * self.size() + column]
+    }
+
+    pub fn set(&mut self, row: usize, column: usize, value: Option<u32>) {
+        let size = self.size();
+        self.cells[row * size + column] = value;
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.cells.iter().all(Option::is_some)
+    }
+
+    /// 1 行で表した盤面。空きマスは `.` になる。
+    pub fn to_line(&self) -> String {
+        self.cells
+            .iter()
+            .map(|cell| match cell {
+                Some(value) => to_char(*value),
+                None => '.',
+            })
+            .collect()
+    }
+
+    pub fn csp(&self) -> Result<CSP<Cell, u32, PuzzleConstraint>, CspError<Cell>> {
+        self.model(|_| {})
+    }
+
+    /// 空きマスのドメインは、同じ行・列・箱にあるヒントを除いた値にする。
+    /// 候補の少ないマスから探索されるように変数を並べる。
+    fn model(
+        &self,
+        mut order_domain: impl FnMut(&mut Vec<u32>),
+    ) -> Result<CSP<Cell, u32, PuzzleConstraint>, CspError<Cell>> {
+        let units = units(self.order);
+        let mut domains = HashMap::new();
+        for (row, column) in self.positions() {
+            let domain = match self.get(row, column) {
+                Some(value) => vec![value],
+                None => {
+                    let used = units
+                        .iter()
+                        .filter(|unit| unit.contains(&(row, column)))
+                        .flatten()
+                        .filter_map(|(r, c)| self.get(*r, *c))
+                        .collect::<HashSet<_>>();
+                    let mut domain = (1..=self.size() as u32)
+                        .filter(|v| !used.contains(v))
+                        .collect();
+                    order_domain(&mut domain);
+                    domain
+                }
+            };
+            domains.insert((row, column), domain);
+        }
+        let variables = self
+            .positions()
+            .sorted_by_key(|cell| domains[cell].len())
+            .collect();
+
+        let mut csp = CSP::new(variables, domains)?;
+        for unit in units {
+            csp.add_constraint(PuzzleConstraint::AllDifferent(unit))?;
+        }
+        Ok(csp)
+    }
+
+    fn positions(&self) -> impl Iterator<Item = Cell> {
+        let size = self.size();
+        (0..size).cartesian_product(0..size)
+    }
+
+    fn with_assignment(&self, assignment: HashMap<Cell, u32>) -> Self {
+        let mut solved = Sudoku::new(self.order);
+        for ((row, column), value) in assignment {
+            solved.set(row, column, Some(value));
+        }
+        solved
+    }
+
+    /// ヒントのせいで候補のなくなったマスがあれば CspError::EmptyDomain を返す。
+    pub fn solve(&self) -> Result<Option<Sudoku>, CspError<Cell>> {
+        let solution = self.csp()?.backtracking_search();
+        Ok(solution.map(|solution| self.with_assignment(solution)))
+    }
+
+    pub fn count_solutions(&self, limit: usize) -> Result<usize, CspError<Cell>> {
+        Ok(self.csp()?.count_solutions(limit))
+    }
+
+    pub fn has_unique_solution(&self) -> bool {
+        self.count_solutions(2) == Ok(1)
+    }
+
+    /// 解が一意な問題を作る。
+    /// 無作為に埋めた盤面から、解が一意である限りマスを消していく。
+    /// order は読み込めるのと同じ 2 から 5 で、それ以外は一辺の長さで InvalidLength を返す。
+    pub fn generate<R: Rng>(order: usize, rng: &mut R) -> Result<Self, ParsePuzzleError> {
+        if !(2..=5).contains(&order) {
+            return Err(ParsePuzzleError::InvalidLength(order.saturating_mul(order)));
+        }
+        let empty = Sudoku::new(order);
+        let csp = empty
+            .model(|domain| domain.shuffle(rng))
+            .expect("an empty grid always has full domains");
+        let mut puzzle = empty.with_assignment(
+            csp.backtracking_search()
+                .expect("an empty grid is always solvable"),
+        );
+
+        let mut positions = puzzle.positions().collect::<Vec<_>>();
+        positions.shuffle(rng);
+        for (row, column) in positions {
+            let value = puzzle.get(row, column);
+            puzzle.set(row, column, None);
+            if !puzzle.has_unique_solution() {
+                puzzle.set(row, column, value);
+            }
+        }
+        Ok(puzzle)
+    }
+}
+
+/// 行・列・箱のマスの組
+fn units(order: usize) -> Vec<Vec<Cell>> {
+    let size = order * order;
+    let mut units = Vec::new();
+    for i in 0..size {
+        units.push((0..size).map(|column| (i, column)).collect());
+        units.push((0..size).map(|row| (row, i)).collect());
+        let (top, left) = (i / order * order, i % order * order);
+        units.push(
+            (top..top + order)
+                .cartesian_product(left..left + order)
+                .collect(),
+        );
+    }
+    units
+}
+
+fn to_char(value: u32) -> char {
+    std::char::from_digit(value, 36)
+        .unwrap_or('?')
+        .to_ascii_uppercase()
+}
+
+/// `53..7....6..195...` のような 1 行の表記を読み込む。
+/// 長さは 16, 81, 256, 625 のいずれかで、空きマスは `.` か `0`。
+/// 10 以上の値は `A` から順に表す。
+impl FromStr for Sudoku {
+    type Err = ParsePuzzleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line = s.trim();
+        let len = line.chars().count();
+        let order = (2..=5)
+            .find(|order: &usize| order.pow(4) == len)
+            .ok_or(ParsePuzzleError::InvalidLength(len))?;
+        let size = (order * order) as u32;
+        let cells = line
+            .chars()
+            .map(|c| match c {
+                '.' | '0' => Ok(None),
+                _ => match c.to_digit(36) {
+                    Some(value) if value <= size => Ok(Some(value)),
+                    _ => Err(ParsePuzzleError::InvalidCharacter(c)),
+                },
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { order, cells })
+    }
+}
+
+impl fmt::Display for Sudoku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = vec!["-".repeat(self.order * 2 - 1); self.order].join("-+-");
+        for row in 0..self.size() {
+            if row > 0 && row % self.order == 0 {
+                writeln!(f, "{}", separator)?;
+            }
+            let line = (0..self.size())
+                .map(|column| match self.get(row, column) {
+                    Some(value) => to_char(value),
+                    None => '.',
+                })
+                .chunks(self.order)
+                .into_iter()
+                .map(|mut chunk| chunk.join(" "))
+                .join(" | ");
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// キラー数独。通常の数独のルールに加え、各ケージの値は異なり、和が指定の値になる。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KillerSudoku {
+    order: usize,
+    cages: Vec<(Vec<Cell>, u32)>,
+}
+
+impl KillerSudoku {
+    pub fn new(order: usize, cages: Vec<(Vec<Cell>, u32)>) -> Self {
+        Self { order, cages }
+    }
+
+    /// ケージごとにまとめて、小さいケージから探索されるように変数を並べる。
+    pub fn csp(&self) -> Result<CSP<Cell, u32, PuzzleConstraint>, CspError<Cell>> {
+        let size = (self.order * self.order) as u32;
+        let mut variables = Vec::new();
+        let mut domains = HashMap::new();
+        for (cells, total) in self.cages.iter().sorted_by_key(|(cells, _)| cells.len()) {
+            for cell in cells {
+                variables.push(*cell);
+                domains.insert(*cell, distinct_sum_domain(size, cells.len(), *total));
+            }
+        }
+
+        let mut csp = CSP::new(variables, domains)?;
+        for unit in units(self.order) {
+            csp.add_constraint(PuzzleConstraint::AllDifferent(unit))?;
+        }
+        for (cells, total) in &self.cages {
+            csp.add_constraint(PuzzleConstraint::AllDifferent(cells.clone()))?;
+            csp.add_constraint(PuzzleConstraint::Cage {
+                cells: cells.clone(),
+                operation: Operation::Add,
+                target: *total,
+            })?;
+        }
+        Ok(csp)
+    }
+
+    /// マスの数では作れない和のケージがあれば CspError::EmptyDomain を返す。
+    pub fn solve(&self) -> Result<Option<Sudoku>, CspError<Cell>> {
+        let solution = self.csp()?.backtracking_search();
+        Ok(solution.map(|solution| Sudoku::new(self.order).with_assignment(solution)))
+    }
+}
+
+/// ケージ ID の盤面に続けて、`a 10` のようにケージの和を並べた表記を読み込む。
+impl FromStr for KillerSudoku {
+    type Err = ParsePuzzleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (size, cages) = parse_cages(s)?;
+        let order = (1..=size)
+            .find(|order| order * order == size)
+            .ok_or(ParsePuzzleError::InvalidLength(size))?;
+        let cages = cages
+            .into_iter()
+            .map(|(cells, total)| match total.parse() {
+                Ok(total) => Ok((cells, total)),
+                Err(_) => Err(ParsePuzzleError::InvalidToken(total)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { order, cages })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const PUZZLE: &str =
+        "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+    const SOLUTION: &str =
+        "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    #[test]
+    fn test_parse_and_solve() {
+        let sudoku = Sudoku::from_str(PUZZLE).unwrap();
+        assert_eq!(sudoku.order(), 3);
+        assert_eq!(sudoku.to_line(), PUZZLE);
+        let solution = sudoku.solve().unwrap().unwrap();
+        assert!(solution.is_complete());
+        assert_eq!(solution.to_line(), SOLUTION);
+        assert!(sudoku.has_unique_solution());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Sudoku::from_str("123"),
+            Err(ParsePuzzleError::InvalidLength(3))
+        );
+        assert_eq!(
+            Sudoku::from_str("12.5............"),
+            Err(ParsePuzzleError::InvalidCharacter('5'))
+        );
+    }
+
+    #[test]
+    fn test_generate_unique() {
+        let clues = |puzzle: &Sudoku| {
+            puzzle
+                .positions()
+                .filter(|(row, column)| puzzle.get(*row, *column).is_some())
+                .collect::<Vec<_>>()
+        };
+        for seed in 0..4 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let puzzle = Sudoku::generate(2, &mut rng).unwrap();
+            assert!(puzzle.has_unique_solution());
+            // どのヒントを消しても解が一意でなくなる
+            for (row, column) in clues(&puzzle) {
+                let mut fewer = puzzle.clone();
+                fewer.set(row, column, None);
+                assert!(!fewer.has_unique_solution());
+            }
+        }
+        let puzzle = Sudoku::generate(3, &mut StdRng::seed_from_u64(27)).unwrap();
+        assert!(puzzle.has_unique_solution());
+        // 9x9 で一意解の問題には少なくとも 17 個のヒントが要る
+        let clues = clues(&puzzle).len();
+        assert!((17..81).contains(&clues), "{}", clues);
+    }
+
+    #[test]
+    fn test_generate_invalid_order() {
+        let mut rng = StdRng::seed_from_u64(27);
+        assert_eq!(
+            Sudoku::generate(0, &mut rng),
+            Err(ParsePuzzleError::InvalidLength(0))
+        );
+        assert_eq!(
+            Sudoku::generate(6, &mut rng),
+            Err(ParsePuzzleError::InvalidLength(36))
+        );
+    }
+
+    #[test]
+    fn test_contradictory_clues() {
+        // 1 行目に 1..8 があり、(0, 8) の列に 9 がある
+        let mut line = "12345678.".to_string() + &".".repeat(72);
+        line.replace_range(80..81, "9");
+        let sudoku = Sudoku::from_str(&line).unwrap();
+        assert_eq!(sudoku.solve(), Err(CspError::EmptyDomain((0, 8))));
+        assert_eq!(
+            sudoku.count_solutions(1),
+            Err(CspError::EmptyDomain((0, 8)))
+        );
+        assert!(!sudoku.has_unique_solution());
+    }
+
+    #[test]
+    fn test_killer_sudoku() {
+        let killer = KillerSudoku::from_str(
+            "aabb
+             cdde
+             cfge
+             hfgi
+             a 3
+             b 7
+             c 7
+             d 4
+             e 3
+             f 5
+             g 5
+             h 2
+             i 4",
+        )
+        .unwrap();
+        let solution = killer.solve().unwrap().unwrap();
+        assert_eq!(solution.to_line(), "1243431234212134");
+    }
+}
@@ -0,0 +1,397 @@
+//! csp で作業の開始時刻を決める（時間割、ジョブショップ）
+use std::collections::HashMap;
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::csp::{Constraint, CspError, SearchStrategy, CSP};
+
+/// 開始時刻を決める作業。resources を共有する作業どうしは同時に行えない。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    pub name: String,
+    pub duration: usize,
+    pub resources: Vec<String>,
+    /// 最も早い開始時刻と最も遅い終了時刻。None なら 0 から horizon まで。
+    pub window: Option<(usize, usize)>,
+}
+
+impl Task {
+    pub fn new(name: &str, duration: usize, resources: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            duration,
+            resources: resources.iter().map(|r| r.to_string()).collect(),
+            window: None,
+        }
+    }
+}
+
+/// 作業の番号を変数、開始時刻を値とする制約。
+/// 資源を共有する作業の組ごとに、どちらを先に行うかを表す変数も加える。
+pub enum SchedulingConstraint {
+    /// before が終わってから after を始める
+    Precedence {
+        before: usize,
+        after: usize,
+        duration: usize,
+    },
+    /// 資源を共有する 2 つの作業が重ならない。変数 order が 0 なら tasks.0、1 なら tasks.1 が先。
+    NoOverlap {
+        tasks: (usize, usize),
+        durations: (usize, usize),
+        order: usize,
+    },
+}
+
+impl Constraint<usize, usize> for SchedulingConstraint {
+    fn variables(&self) -> Vec<usize> {
+        match self {
+            SchedulingConstraint::Precedence { before, after, .. } => vec![*before, *after],
+            SchedulingConstraint::NoOverlap { tasks, order, .. } => {
+                vec![tasks.0, tasks.1, *order]
+            }
+        }
+    }
+
+    fn satisfied(&self, assignment: &HashMap<usize, usize>) -> bool {
+        match self {
+            SchedulingConstraint::Precedence {
+                before,
+                after,
+                duration,
+            } => match (assignment.get(before), assignment.get(after)) {
+                (Some(b), Some(a)) => b + duration <= *a,
+                _ => true,
+            },
+            SchedulingConstraint::NoOverlap {
+                tasks,
+                durations,
+                order,
+            } => match (assignment.get(&tasks.0), assignment.get(&tasks.1)) {
+                (Some(s), Some(t)) => match assignment.get(order) {
+                    Some(0) => s + durations.0 <= *t,
+                    Some(_) => t + durations.1 <= *s,
+                    None => s + durations.0 <= *t || t + durations.1 <= *s,
+                },
+                _ => true,
+            },
+        }
+    }
+
+    /// 開始時刻の範囲 (ドメインの最小と最大) だけを見て狭める。
+    /// NoOverlap は片方の順序しか取れなくなったら order を決め、その順序の Precedence として扱う。
+    fn propagate(&self, domains: &mut HashMap<usize, Vec<usize>>) -> bool {
+        let bounds = |domains: &HashMap<usize, Vec<usize>>, task: usize| {
+            let values = &domains[&task];
+            (values[0], values[values.len() - 1])
+        };
+        let (before, after, duration) = match self {
+            SchedulingConstraint::Precedence {
+                before,
+                after,
+                duration,
+            } => (*before, *after, *duration),
+            SchedulingConstraint::NoOverlap {
+                tasks,
+                durations,
+                order,
+            } => {
+                let (first, second) = (bounds(domains, tasks.0), bounds(domains, tasks.1));
+                let orders = &domains[order];
+                let first_before = orders.contains(&0) && first.0 + durations.0 <= second.1;
+                let second_before = orders.contains(&1) && second.0 + durations.1 <= first.1;
+                let (order_value, precedence) = match (first_before, second_before) {
+                    (false, false) => return false,
+                    (true, false) => (0, (tasks.0, tasks.1, durations.0)),
+                    (false, true) => (1, (tasks.1, tasks.0, durations.1)),
+                    (true, true) => return true,
+                };
+                domains.insert(*order, vec![order_value]);
+                precedence
+            }
+        };
+        let earliest = bounds(domains, before).0 + duration;
+        let latest = bounds(domains, after).1;
+        domains.get_mut(&after).unwrap().retain(|s| *s >= earliest);
+        domains
+            .get_mut(&before)
+            .unwrap()
+            .retain(|s| s + duration <= latest);
+        !domains[&before].is_empty() && !domains[&after].is_empty()
+    }
+}
+
+/// 0 から horizon までに tasks を行う問題
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheduling {
+    pub horizon: usize,
+    pub tasks: Vec<Task>,
+    /// (before, after) は before が終わってから after を始めること
+    pub precedences: Vec<(usize, usize)>,
+}
+
+impl Scheduling {
+    pub fn new(horizon: usize) -> Self {
+        Self {
+            horizon,
+            tasks: Vec::new(),
+            precedences: Vec::new(),
+        }
+    }
+
+    /// 作業を加えて、その番号を返す。
+    pub fn add_task(&mut self, task: Task) -> usize {
+        self.tasks.push(task);
+        self.tasks.len() - 1
+    }
+
+    /// before と after は add_task が返した番号でなければならない。
+    pub fn add_precedence(&mut self, before: usize, after: usize) -> Result<(), CspError<usize>> {
+        if let Some(unknown) = [before, after].into_iter().find(|i| *i >= self.tasks.len()) {
+            return Err(CspError::UnknownVariable(unknown));
+        }
+        self.precedences.push((before, after));
+        Ok(())
+    }
+
+    /// 順序関係をたどって、各作業の最も早い開始時刻と最も遅い終了時刻を狭める。
+    /// 順序関係が循環していると範囲が空になる。
+    fn windows(&self) -> Vec<(usize, usize)> {
+        let mut windows = self
+            .tasks
+            .iter()
+            .map(|task| {
+                let (earliest, latest_end) = task.window.unwrap_or((0, self.horizon));
+                (earliest, latest_end.min(self.horizon))
+            })
+            .collect::<Vec<_>>();
+        // 循環がなければ作業の数だけ繰り返すうちに変わらなくなる
+        for _ in 0..=self.tasks.len() {
+            let mut changed = false;
+            for (before, after) in &self.precedences {
+                let earliest = windows[*before].0 + self.tasks[*before].duration;
+                if windows[*after].0 < earliest {
+                    windows[*after].0 = earliest;
+                    changed = true;
+                }
+                let latest_end = windows[*after]
+                    .1
+                    .saturating_sub(self.tasks[*after].duration);
+                if windows[*before].1 > latest_end {
+                    windows[*before].1 = latest_end;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return windows;
+            }
+        }
+        vec![(1, 0); self.tasks.len()]
+    }
+
+    /// 早く始められる作業から順に、早い時刻から試すように並べる。
+    /// 資源を共有する作業の組の順序を表す変数は、作業の数から順に番号をつけて後ろに加える。
+    /// 時間内に収まらない作業があれば CspError::EmptyDomain になる。
+    pub fn csp(&self) -> Result<CSP<usize, usize, SchedulingConstraint>, CspError<usize>> {
+        let windows = self.windows();
+        let mut domains = HashMap::new();
+        for (i, (earliest, latest_end)) in windows.iter().enumerate() {
+            let duration = self.tasks[i].duration;
+            let domain = if earliest + duration <= *latest_end {
+                (*earliest..=latest_end - duration).collect()
+            } else {
+                Vec::new()
+            };
+            domains.insert(i, domain);
+        }
+        let pairs = (0..self.tasks.len())
+            .tuple_combinations()
+            .filter(|(i, j)| {
+                let (first, second) = (&self.tasks[*i], &self.tasks[*j]);
+                first.resources.iter().any(|r| second.resources.contains(r))
+            })
+            .collect::<Vec<(usize, usize)>>();
+        let orders = (self.tasks.len()..).take(pairs.len()).collect::<Vec<_>>();
+        for order in &orders {
+            domains.insert(*order, vec![0, 1]);
+        }
+        let variables = (0..self.tasks.len())
+            .sorted_by_key(|i| (windows[*i].0, windows[*i].1))
+            .chain(orders.iter().copied())
+            .collect();
+        let mut csp = CSP::new(variables, domains)?;
+        for (before, after) in &self.precedences {
+            csp.add_constraint(SchedulingConstraint::Precedence {
+                before: *before,
+                after: *after,
+                duration: self.tasks[*before].duration,
+            })?;
+        }
+        for ((i, j), order) in pairs.into_iter().zip(orders) {
+            csp.add_constraint(SchedulingConstraint::NoOverlap {
+                tasks: (i, j),
+                durations: (self.tasks[i].duration, self.tasks[j].duration),
+                order,
+            })?;
+        }
+        Ok(csp)
+    }
+
+    /// 作業の開始時刻の範囲を制約の伝播で狭めながら探す。
+    /// 期限内に収まらない作業があれば CspError::EmptyDomain になる。
+    pub fn solve(&self) -> Result<Option<Schedule>, CspError<usize>> {
+        let (solution, _) = self.csp()?.search(SearchStrategy::Propagation);
+        Ok(solution.map(|solution| Schedule {
+            tasks: self.tasks.clone(),
+            starts: (0..self.tasks.len()).map(|i| solution[&i]).collect(),
+        }))
+    }
+
+    /// 期限を直前に見つけた makespan より 1 だけ短くして解き直し、makespan が最小の日程を返す。
+    pub fn minimize(&self) -> Result<Option<Schedule>, CspError<usize>> {
+        let mut scheduling = self.clone();
+        let mut best = None;
+        loop {
+            match scheduling.solve() {
+                Ok(Some(schedule)) => {
+                    let makespan = schedule.makespan();
+                    best = Some(schedule);
+                    if makespan == 0 {
+                        break;
+                    }
+                    scheduling.horizon = makespan - 1;
+                }
+                Ok(None) => break,
+                // 縮めた期限に収まらない作業があれば、それより短い日程はない
+                Err(CspError::EmptyDomain(_)) if best.is_some() => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(best)
+    }
+}
+
+/// 決まった開始時刻。starts は Scheduling の tasks と同じ順。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    pub tasks: Vec<Task>,
+    pub starts: Vec<usize>,
+}
+
+impl Schedule {
+    /// すべての作業が終わる時刻
+    pub fn makespan(&self) -> usize {
+        self.tasks
+            .iter()
+            .zip(&self.starts)
+            .map(|(task, start)| start + task.duration)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// 資源ごとに 1 行のガントチャート。作業は名前の最初の文字で表す。
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let resources = self
+            .tasks
+            .iter()
+            .flat_map(|task| task.resources.iter())
+            .unique()
+            .sorted()
+            .collect::<Vec<_>>();
+        let width = resources.iter().map(|r| r.len()).max().unwrap_or(0);
+        for resource in resources {
+            let mut line = vec!['.'; self.makespan()];
+            for (task, start) in self.tasks.iter().zip(&self.starts) {
+                if task.resources.contains(resource) {
+                    let label = task.name.chars().next().unwrap_or('#');
+                    line[*start..start + task.duration].fill(label);
+                }
+            }
+            writeln!(
+                f,
+                "{:>width$} |{}|",
+                resource,
+                line.into_iter().collect::<String>(),
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_shop() {
+        // 2 つのジョブが 2 台の機械を逆の順に使う
+        let mut scheduling = Scheduling::new(5);
+        let a1 = scheduling.add_task(Task::new("a1", 2, &["m1"]));
+        let a2 = scheduling.add_task(Task::new("a2", 1, &["m2"]));
+        let b1 = scheduling.add_task(Task::new("b1", 2, &["m2"]));
+        let b2 = scheduling.add_task(Task::new("b2", 2, &["m1"]));
+        scheduling.add_precedence(a1, a2).unwrap();
+        scheduling.add_precedence(b1, b2).unwrap();
+        let schedule = scheduling.solve().unwrap().unwrap();
+        assert!(schedule.starts[a1] + 2 <= schedule.starts[a2]);
+        assert!(schedule.starts[b1] + 2 <= schedule.starts[b2]);
+        assert!(schedule.makespan() <= 5);
+        scheduling.horizon = 10;
+        assert_eq!(scheduling.minimize().unwrap().unwrap().makespan(), 4);
+        // b1 と b2 を続けて行うと 4 かかる
+        scheduling.horizon = 3;
+        assert_eq!(scheduling.solve(), Err(CspError::EmptyDomain(b1)));
+        assert_eq!(
+            scheduling.add_precedence(a1, 4),
+            Err(CspError::UnknownVariable(4))
+        );
+    }
+
+    #[test]
+    fn test_no_schedule() {
+        // どちらの作業も期限に収まるが、同じ機械で重ならずには収まらない
+        let mut scheduling = Scheduling::new(3);
+        scheduling.add_task(Task::new("a", 2, &["m"]));
+        scheduling.add_task(Task::new("b", 2, &["m"]));
+        assert_eq!(scheduling.solve(), Ok(None));
+        assert_eq!(scheduling.minimize(), Ok(None));
+    }
+
+    #[test]
+    fn test_timetable() {
+        // 先生と教室を共有する授業。数学は 2 時限目以降に限る。
+        let mut scheduling = Scheduling::new(3);
+        scheduling.add_task(Task::new("english", 1, &["smith", "room1"]));
+        let mut math = Task::new("math", 1, &["jones", "room1"]);
+        math.window = Some((1, 3));
+        let math = scheduling.add_task(math);
+        scheduling.add_task(Task::new("history", 1, &["smith", "room2"]));
+        let schedule = scheduling.solve().unwrap().unwrap();
+        assert!(schedule.starts[math] >= 1);
+        assert_ne!(schedule.starts[0], schedule.starts[math]);
+        assert_ne!(schedule.starts[0], schedule.starts[2]);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let mut scheduling = Scheduling::new(10);
+        let a = scheduling.add_task(Task::new("a", 1, &[]));
+        let b = scheduling.add_task(Task::new("b", 1, &[]));
+        scheduling.add_precedence(a, b).unwrap();
+        scheduling.add_precedence(b, a).unwrap();
+        assert!(matches!(scheduling.csp(), Err(CspError::EmptyDomain(_))));
+    }
+
+    #[test]
+    fn test_gantt() {
+        let schedule = Schedule {
+            tasks: vec![Task::new("a", 2, &["m1"]), Task::new("b", 1, &["m2", "m1"])],
+            starts: vec![0, 2],
+        };
+        assert_eq!(schedule.to_string(), "m1 |aab|\nm2 |..b|\n");
+    }
+}
@@ -23,6 +23,12 @@ pub struct TTTBoard {
     turn: TTTPiece,
 }
 
+impl Default for TTTBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TTTBoard {
     pub fn new() -> Self {
         Self {
@@ -59,7 +65,7 @@ impl Board for TTTBoard {
     }
 
     fn move_(&self, location: &Self::Move) -> Self {
-        let mut temp_position = self.position.clone();
+        let mut temp_position = self.position;
         temp_position[*location] = self.turn;
         Self {
             position: temp_position,
@@ -0,0 +1,367 @@
+//! 巡回セールスマン問題。TSPLIB 形式の読み込みと、遺伝的アルゴリズムと局所探索による解法、
+//! 小さな問題のための厳密解法。
+use std::fmt;
+use std::str::FromStr;
+
+pub mod exact;
+pub mod genetic;
+pub mod local_search;
+
+/// TSPLIB 形式を読み込むときのエラー。行番号は 1 から数える。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTsplibError {
+    /// 解釈できない行
+    InvalidLine(usize),
+    /// 対応していない TYPE、EDGE_WEIGHT_TYPE、EDGE_WEIGHT_FORMAT と、非対称な距離
+    Unsupported(String),
+    /// 必要な項目や節がない、または数が DIMENSION と合わない
+    Missing(&'static str),
+}
+
+impl fmt::Display for ParseTsplibError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseTsplibError::InvalidLine(line) => write!(f, "line {}: invalid line", line),
+            ParseTsplibError::Unsupported(value) => write!(f, "unsupported {}", value),
+            ParseTsplibError::Missing(name) => write!(f, "missing or incomplete {}", name),
+        }
+    }
+}
+
+impl std::error::Error for ParseTsplibError {}
+
+/// 都市の数と都市間の距離。距離は対称で、TSPLIB と同じく整数。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instance {
+    name: String,
+    dimension: usize,
+    distances: Vec<i64>,
+}
+
+impl Instance {
+    /// distances は dimension × dimension の対称行列を行ごとに並べたもの。都市は 1 つ以上。
+    pub fn new(name: &str, distances: Vec<Vec<i64>>) -> Self {
+        let dimension = distances.len();
+        assert!(dimension >= 1);
+        assert!(distances.iter().all(|row| row.len() == dimension));
+        assert!((0..dimension).all(|i| (0..i).all(|j| distances[i][j] == distances[j][i])));
+        Self {
+            name: name.to_string(),
+            dimension,
+            distances: distances.concat(),
+        }
+    }
+
+    /// 平面上の点。距離は TSPLIB の EUC_2D と同じく、ユークリッド距離を四捨五入する。
+    pub fn from_points(name: &str, points: &[(f64, f64)]) -> Self {
+        Self::from_coordinates(name, points, |d| d.round() as i64)
+    }
+
+    fn from_coordinates(name: &str, points: &[(f64, f64)], round: fn(f64) -> i64) -> Self {
+        let distances = points
+            .iter()
+            .map(|(x1, y1)| {
+                points
+                    .iter()
+                    .map(|(x2, y2)| round((x1 - x2).hypot(y1 - y2)))
+                    .collect()
+            })
+            .collect();
+        Self::new(name, distances)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 都市の数
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    pub fn distance(&self, from: usize, to: usize) -> i64 {
+        self.distances[from * self.dimension + to]
+    }
+
+    /// 最後の都市から最初の都市へ戻るまでの長さ
+    pub fn tour_length(&self, cities: &[usize]) -> i64 {
+        cities
+            .iter()
+            .zip(cities.iter().cycle().skip(1))
+            .map(|(from, to)| self.distance(*from, *to))
+            .sum()
+    }
+
+    /// start から、まだ訪れていない最も近い都市へ順に進む巡回路
+    pub fn nearest_neighbour(&self, start: usize) -> Vec<usize> {
+        let mut visited = vec![false; self.dimension];
+        let mut cities = vec![start];
+        visited[start] = true;
+        while cities.len() < self.dimension {
+            let last = *cities.last().unwrap();
+            let next = (0..self.dimension)
+                .filter(|city| !visited[*city])
+                .min_by_key(|city| self.distance(last, *city))
+                .unwrap();
+            visited[next] = true;
+            cities.push(next);
+        }
+        cities
+    }
+}
+
+/// 都市を訪れる順と、その巡回路の長さ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tour {
+    pub cities: Vec<usize>,
+    pub length: i64,
+}
+
+impl Tour {
+    pub fn new(instance: &Instance, cities: Vec<usize>) -> Self {
+        let length = instance.tour_length(&cities);
+        Self { cities, length }
+    }
+
+    /// 最適値からどれだけ長いか (%)。最適値が 0 なら、長さも 0 のときだけ 0 で、ほかは無限大。
+    pub fn gap(&self, optimum: i64) -> f64 {
+        if optimum == 0 {
+            return if self.length == 0 { 0.0 } else { f64::INFINITY };
+        }
+        (self.length - optimum) as f64 / optimum as f64 * 100.0
+    }
+}
+
+/// EDGE_WEIGHT_SECTION の数が埋める (行, 列) の順
+fn explicit_positions(format: &str, n: usize) -> Option<Vec<(usize, usize)>> {
+    let pairs = |keep: fn(usize, usize) -> bool| {
+        (0..n)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .filter(|(i, j)| keep(*i, *j))
+            .collect()
+    };
+    // 対称なので、列ごとの上三角は行ごとの下三角と同じ順になる
+    match format {
+        "FULL_MATRIX" => Some(pairs(|_, _| true)),
+        "UPPER_ROW" | "LOWER_COL" => Some(pairs(|i, j| i < j)),
+        "LOWER_ROW" | "UPPER_COL" => Some(pairs(|i, j| i > j)),
+        "UPPER_DIAG_ROW" | "LOWER_DIAG_COL" => Some(pairs(|i, j| i <= j)),
+        "LOWER_DIAG_ROW" | "UPPER_DIAG_COL" => Some(pairs(|i, j| i >= j)),
+        _ => None,
+    }
+}
+
+/// TSPLIB の TSP 形式。EDGE_WEIGHT_TYPE は EUC_2D、CEIL_2D と EXPLICIT に対応する。
+impl FromStr for Instance {
+    type Err = ParseTsplibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = String::new();
+        let mut dimension = None;
+        let mut weight_type = None;
+        let mut weight_format = None;
+        let mut coordinates = Vec::new();
+        let mut weights = Vec::new();
+        let mut section = None;
+        for (i, line) in s.lines().enumerate() {
+            let number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line == "EOF" {
+                continue;
+            }
+            // 節の見出しには ':' がつくこともある
+            let heading = line.trim_end_matches(':').trim_end();
+            if heading.ends_with("_SECTION") {
+                section = Some(heading.to_string());
+                continue;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim().to_string();
+                match key.trim() {
+                    "NAME" => name = value,
+                    "TYPE" if value != "TSP" => {
+                        return Err(ParseTsplibError::Unsupported(format!("TYPE {}", value)))
+                    }
+                    // 都市が 1 つもない問題は解けないので、DIMENSION は 1 以上
+                    "DIMENSION" => {
+                        dimension = Some(
+                            value
+                                .parse::<usize>()
+                                .ok()
+                                .filter(|dimension| *dimension >= 1)
+                                .ok_or(ParseTsplibError::InvalidLine(number))?,
+                        )
+                    }
+                    "EDGE_WEIGHT_TYPE" => weight_type = Some(value),
+                    "EDGE_WEIGHT_FORMAT" => weight_format = Some(value),
+                    _ => {}
+                }
+                section = None;
+                continue;
+            }
+            let numbers = line
+                .split_whitespace()
+                .map(|token| token.parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| ParseTsplibError::InvalidLine(number))?;
+            match section.as_deref() {
+                Some("NODE_COORD_SECTION") if numbers.len() == 3 => {
+                    coordinates.push((numbers[1], numbers[2]))
+                }
+                Some("EDGE_WEIGHT_SECTION") => weights.extend(numbers),
+                Some("DISPLAY_DATA_SECTION") => {}
+                _ => return Err(ParseTsplibError::InvalidLine(number)),
+            }
+        }
+
+        let dimension = dimension.ok_or(ParseTsplibError::Missing("DIMENSION"))?;
+        match weight_type.as_deref() {
+            Some("EUC_2D" | "CEIL_2D") if coordinates.len() != dimension => {
+                Err(ParseTsplibError::Missing("NODE_COORD_SECTION"))
+            }
+            Some("EUC_2D") => Ok(Self::from_points(&name, &coordinates)),
+            Some("CEIL_2D") => Ok(Self::from_coordinates(&name, &coordinates, |d| {
+                d.ceil() as i64
+            })),
+            Some("EXPLICIT") => {
+                let format =
+                    weight_format.ok_or(ParseTsplibError::Missing("EDGE_WEIGHT_FORMAT"))?;
+                let positions = explicit_positions(&format, dimension).ok_or_else(|| {
+                    ParseTsplibError::Unsupported(format!("EDGE_WEIGHT_FORMAT {}", format))
+                })?;
+                if positions.len() != weights.len() {
+                    return Err(ParseTsplibError::Missing("EDGE_WEIGHT_SECTION"));
+                }
+                let mut distances = vec![vec![0; dimension]; dimension];
+                for ((i, j), weight) in positions.into_iter().zip(weights) {
+                    let weight = weight as i64;
+                    // FULL_MATRIX の下三角は、先に読んだ上三角と同じでなければならない
+                    if i > j && format == "FULL_MATRIX" && distances[i][j] != weight {
+                        return Err(ParseTsplibError::Unsupported(
+                            "asymmetric EDGE_WEIGHT_SECTION".to_string(),
+                        ));
+                    }
+                    distances[i][j] = weight;
+                    distances[j][i] = weight;
+                }
+                Ok(Self::new(&name, distances))
+            }
+            Some(other) => Err(ParseTsplibError::Unsupported(format!(
+                "EDGE_WEIGHT_TYPE {}",
+                other
+            ))),
+            None => Err(ParseTsplibError::Missing("EDGE_WEIGHT_TYPE")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_euc_2d() {
+        let instance = Instance::from_str(
+            "NAME : square
+TYPE : TSP
+DIMENSION : 4
+EDGE_WEIGHT_TYPE : EUC_2D
+NODE_COORD_SECTION
+1 0 0
+2 0 3
+3 4 3
+4 4 0
+EOF",
+        )
+        .unwrap();
+        assert_eq!(instance.name(), "square");
+        assert_eq!(instance.distance(0, 2), 5);
+        assert_eq!(instance.tour_length(&[0, 1, 2, 3]), 14);
+        assert_eq!(instance.tour_length(&[0, 2, 1, 3]), 18);
+    }
+
+    #[test]
+    fn test_parse_explicit() {
+        let lower = "NAME: lower
+TYPE: TSP
+DIMENSION: 3
+EDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: LOWER_DIAG_ROW
+EDGE_WEIGHT_SECTION
+0 1 0
+2 3 0
+EOF";
+        let full = "NAME: full
+TYPE: TSP
+DIMENSION: 3
+EDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: FULL_MATRIX
+EDGE_WEIGHT_SECTION
+0 1 2
+1 0 3
+2 3 0
+EOF";
+        let parsed = Instance::from_str(lower).unwrap();
+        assert_eq!(
+            parsed.distances,
+            Instance::from_str(full).unwrap().distances
+        );
+        assert_eq!(parsed.distance(2, 1), 3);
+        assert_eq!(
+            Instance::from_str(&full.replace("FULL_MATRIX", "UPPER_ROW")),
+            Err(ParseTsplibError::Missing("EDGE_WEIGHT_SECTION"))
+        );
+        assert_eq!(
+            Instance::from_str(&full.replace("1 0 3", "4 0 3")),
+            Err(ParseTsplibError::Unsupported(
+                "asymmetric EDGE_WEIGHT_SECTION".to_string()
+            ))
+        );
+        assert_eq!(
+            Instance::from_str(&full.replace("EXPLICIT", "GEO")),
+            Err(ParseTsplibError::Unsupported(
+                "EDGE_WEIGHT_TYPE GEO".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_parse_dimension() {
+        let single = "NAME: single
+DIMENSION: 1
+EDGE_WEIGHT_TYPE: EUC_2D
+NODE_COORD_SECTION
+1 0 0
+EOF";
+        let instance = Instance::from_str(single).unwrap();
+        assert_eq!(instance.nearest_neighbour(0), vec![0]);
+        assert_eq!(Tour::new(&instance, vec![0]).length, 0);
+        assert_eq!(
+            Instance::from_str(&single.replace("DIMENSION: 1", "DIMENSION: 0")),
+            Err(ParseTsplibError::InvalidLine(2))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_asymmetric() {
+        Instance::new("asymmetric", vec![vec![0, 1], vec![2, 0]]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_empty() {
+        Instance::new("empty", Vec::new());
+    }
+
+    #[test]
+    fn test_gap() {
+        let tour = |length| Tour {
+            cities: Vec::new(),
+            length,
+        };
+        assert_eq!(tour(110).gap(100), 10.0);
+        assert_eq!(tour(0).gap(0), 0.0);
+        assert_eq!(tour(5).gap(0), f64::INFINITY);
+    }
+}
@@ -0,0 +1,330 @@
+//! 最適な巡回路を求める厳密解法。都市が 20 ほどまでの問題に向く。
+use super::local_search::improve;
+use super::{Instance, Tour};
+
+/// held_karp で解ける都市の数の上限。表は 2^(n-1) (n-1) 個の i64 と u8 からなり、
+/// 20 都市で約 90 MB、24 都市なら約 1.7 GB になる。
+pub const HELD_KARP_LIMIT: usize = 20;
+
+/// Held–Karp の動的計画法。都市 0 から出て、訪れた都市の集合と最後の都市ごとに最短の長さを覚える。
+/// 時間は O(2^n n^2)、記憶領域は O(2^n n) なので、都市は HELD_KARP_LIMIT までにする。
+pub fn held_karp(instance: &Instance) -> Tour {
+    let n = instance.dimension();
+    assert!(n <= HELD_KARP_LIMIT, "Held-Karp needs O(2^n n) memory");
+    if n <= 3 {
+        return Tour::new(instance, (0..n).collect());
+    }
+    // 都市 1..n を 0..m のビットで表す
+    let m = n - 1;
+    let full = (1usize << m) - 1;
+    let d = |a: usize, b: usize| instance.distance(a, b);
+    let mut cost = vec![i64::MAX; (full + 1) * m];
+    let mut parent = vec![0u8; (full + 1) * m];
+    for j in 0..m {
+        cost[(1 << j) * m + j] = d(0, j + 1);
+    }
+    for mask in 1..=full {
+        for j in (0..m).filter(|j| mask >> j & 1 == 1) {
+            let c = cost[mask * m + j];
+            if c == i64::MAX {
+                continue;
+            }
+            for k in (0..m).filter(|k| mask >> k & 1 == 0) {
+                let next = (mask | 1 << k) * m + k;
+                let length = c + d(j + 1, k + 1);
+                if length < cost[next] {
+                    cost[next] = length;
+                    parent[next] = j as u8;
+                }
+            }
+        }
+    }
+    let mut last = (0..m)
+        .min_by_key(|j| cost[full * m + j] + d(j + 1, 0))
+        .unwrap();
+    let mut mask = full;
+    let mut cities = Vec::new();
+    loop {
+        cities.push(last + 1);
+        let previous = parent[mask * m + last] as usize;
+        mask ^= 1 << last;
+        if mask == 0 {
+            break;
+        }
+        last = previous;
+    }
+    cities.push(0);
+    cities.reverse();
+    Tour::new(instance, cities)
+}
+
+/// nodes を結ぶ最小全域木の重さと辺 (Prim 法)
+fn spanning_tree(
+    nodes: &[usize],
+    weight: impl Fn(usize, usize) -> f64,
+) -> (f64, Vec<(usize, usize)>) {
+    let Some((first, rest)) = nodes.split_first() else {
+        return (0.0, Vec::new());
+    };
+    // まだ木に入っていない頂点と、木への最短の辺
+    let mut remaining = rest
+        .iter()
+        .map(|node| (*node, *first, weight(*first, *node)))
+        .collect::<Vec<_>>();
+    let mut total = 0.0;
+    let mut edges = Vec::new();
+    while !remaining.is_empty() {
+        let i = (0..remaining.len())
+            .min_by(|a, b| remaining[*a].2.total_cmp(&remaining[*b].2))
+            .unwrap();
+        let (node, parent, w) = remaining.swap_remove(i);
+        total += w;
+        edges.push((parent, node));
+        for (other, parent, w) in remaining.iter_mut() {
+            let candidate = weight(node, *other);
+            if candidate < *w {
+                *w = candidate;
+                *parent = node;
+            }
+        }
+    }
+    (total, edges)
+}
+
+/// 都市ごとの罰則 penalties を足した距離での 1-tree。都市 0 を除いた最小全域木に、
+/// 都市 0 から出る短い 2 本の辺を足したもの。重さと各都市の次数を返す。
+fn one_tree(instance: &Instance, penalties: &[f64]) -> (f64, Vec<i64>) {
+    let n = instance.dimension();
+    let weight = |a: usize, b: usize| instance.distance(a, b) as f64 + penalties[a] + penalties[b];
+    let (mut total, edges) = spanning_tree(&(1..n).collect::<Vec<_>>(), weight);
+    let mut degrees = vec![0; n];
+    for (a, b) in edges {
+        degrees[a] += 1;
+        degrees[b] += 1;
+    }
+    let mut nearest = (1..n).collect::<Vec<_>>();
+    nearest.sort_by(|a, b| weight(0, *a).total_cmp(&weight(0, *b)));
+    for city in &nearest[..2] {
+        total += weight(0, *city);
+        degrees[0] += 1;
+        degrees[*city] += 1;
+    }
+    (total, degrees)
+}
+
+/// 1-tree の下界を最も大きくする都市ごとの罰則を劣勾配法で探す (Held–Karp の下界)。
+/// 巡回路ではどの都市の次数も 2 なので、罰則を足しても巡回路の長さは 2 Σ罰則 だけ増えるだけで、
+/// 1-tree の重さから 2 Σ罰則 を引いたものは常に最適な長さ以下になる。
+/// upper は既知の巡回路の長さで、歩幅を決めるのに使う。
+fn penalties(instance: &Instance, upper: i64) -> (f64, Vec<f64>) {
+    let n = instance.dimension();
+    let mut penalties = vec![0.0; n];
+    let mut best = (f64::NEG_INFINITY, penalties.clone());
+    let mut step = 2.0;
+    let mut stalled = 0;
+    for _ in 0..100 * n {
+        let (weight, degrees) = one_tree(instance, &penalties);
+        let bound = weight - 2.0 * penalties.iter().sum::<f64>();
+        if bound > best.0 + 1e-9 {
+            best = (bound, penalties.clone());
+            stalled = 0;
+        } else {
+            stalled += 1;
+            if stalled == 2 * n {
+                step /= 2.0;
+                stalled = 0;
+            }
+        }
+        let norm = degrees
+            .iter()
+            .map(|d| ((d - 2) * (d - 2)) as f64)
+            .sum::<f64>();
+        // すべての次数が 2 なら 1-tree は巡回路で、この下界は最適値に等しい
+        if norm == 0.0 || step < 1e-6 || best.0 > upper as f64 - 1.0 {
+            break;
+        }
+        let t = step * (upper as f64 - bound) / norm;
+        for (penalty, degree) in penalties.iter_mut().zip(&degrees) {
+            *penalty += t * (degree - 2) as f64;
+        }
+    }
+    best
+}
+
+/// 1-tree の下界。巡回路は 1-tree の一種なので、最適な長さはこれ以上になる。
+/// 都市ごとの罰則を劣勾配法で調整し、罰則なしの 1-tree より強い下界にする。
+pub fn one_tree_bound(instance: &Instance) -> i64 {
+    let n = instance.dimension();
+    if n <= 3 {
+        return Tour::new(instance, (0..n).collect()).length;
+    }
+    let mut cities = instance.nearest_neighbour(0);
+    improve(instance, &mut cities);
+    let upper = instance.tour_length(&cities);
+    (penalties(instance, upper).0 - 1e-6).ceil() as i64
+}
+
+/// 分枝限定法の探索の状態
+struct Search<'a> {
+    instance: &'a Instance,
+    penalties: Vec<f64>,
+    best: Tour,
+    path: Vec<usize>,
+    visited: Vec<bool>,
+    nodes: usize,
+}
+
+impl Search<'_> {
+    /// まだ訪れていない都市を通って last から都市 0 へ戻る道の長さの下界。
+    /// その道は罰則つきの距離での全域木なので、全域木の重さから道の上の罰則を引く。
+    fn remaining_bound(&self, last: usize, unvisited: &[usize]) -> f64 {
+        let mut nodes = unvisited.to_vec();
+        nodes.extend([last, 0]);
+        let weight = |a: usize, b: usize| {
+            self.instance.distance(a, b) as f64 + self.penalties[a] + self.penalties[b]
+        };
+        let (tree, _) = spanning_tree(&nodes, weight);
+        tree - 2.0 * unvisited.iter().map(|c| self.penalties[*c]).sum::<f64>()
+            - self.penalties[last]
+            - self.penalties[0]
+    }
+
+    /// path を延ばしていく。
+    fn search(&mut self, length: i64) {
+        self.nodes += 1;
+        let n = self.instance.dimension();
+        let last = *self.path.last().unwrap();
+        if self.path.len() == n {
+            let length = length + self.instance.distance(last, 0);
+            if length < self.best.length {
+                self.best = Tour {
+                    cities: self.path.clone(),
+                    length,
+                };
+            }
+            return;
+        }
+        let mut unvisited = (0..n).filter(|c| !self.visited[*c]).collect::<Vec<_>>();
+        // 距離は整数なので、下界を切り上げて暫定解に届くなら刈る
+        let bound = length as f64 + self.remaining_bound(last, &unvisited);
+        if (bound - 1e-6).ceil() as i64 >= self.best.length {
+            return;
+        }
+        // 近い都市から試すと、早く良い巡回路が見つかって枝を刈りやすい
+        unvisited.sort_by_key(|c| self.instance.distance(last, *c));
+        for city in unvisited {
+            let next = length + self.instance.distance(last, city);
+            if next >= self.best.length {
+                continue;
+            }
+            self.visited[city] = true;
+            self.path.push(city);
+            self.search(next);
+            self.path.pop();
+            self.visited[city] = false;
+        }
+    }
+}
+
+/// 分枝限定法。最近傍法を局所探索で磨いた巡回路を暫定解にし、
+/// 1-tree の罰則で強めた全域木の下界が暫定解に届く部分的な巡回路を刈る。
+pub fn branch_and_bound(instance: &Instance) -> Tour {
+    let n = instance.dimension();
+    if n <= 3 {
+        return Tour::new(instance, (0..n).collect());
+    }
+    let mut cities = instance.nearest_neighbour(0);
+    improve(instance, &mut cities);
+    let best = Tour::new(instance, cities);
+    let (bound, penalties) = penalties(instance, best.length);
+    if (bound - 1e-6).ceil() as i64 >= best.length {
+        return best;
+    }
+    let tour = search(instance, best, penalties);
+    tracing::info!("1-tree bound {:.1}, optimum {}", bound, tour.length);
+    tour
+}
+
+/// 暫定解 best から探索し、それより短い巡回路があればそれを返す。
+fn search(instance: &Instance, best: Tour, penalties: Vec<f64>) -> Tour {
+    let mut visited = vec![false; instance.dimension()];
+    visited[0] = true;
+    let mut search = Search {
+        instance,
+        penalties,
+        best,
+        path: vec![0],
+        visited,
+        nodes: 0,
+    };
+    search.search(0);
+    tracing::info!("{} nodes searched", search.nodes);
+    search.best
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use itertools::Itertools;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn brute_force(instance: &Instance) -> i64 {
+        (1..instance.dimension())
+            .permutations(instance.dimension() - 1)
+            .map(|rest| {
+                let mut cities = vec![0];
+                cities.extend(rest);
+                instance.tour_length(&cities)
+            })
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_random_instances() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for n in 4..=8 {
+            let points = (0..n)
+                .map(|_| (rng.gen_range(0.0..100.0), rng.gen_range(0.0..100.0)))
+                .collect::<Vec<_>>();
+            let instance = Instance::from_points("random", &points);
+            let optimum = brute_force(&instance);
+            assert!(one_tree_bound(&instance) <= optimum);
+            // 罰則なしで、悪い暫定解からでも探索が最適解にたどり着く
+            let worst = Tour::new(&instance, (0..n).collect());
+            let searched = search(&instance, worst, vec![0.0; n]);
+            assert_eq!(searched.length, optimum);
+            for tour in [held_karp(&instance), branch_and_bound(&instance)] {
+                assert_eq!(tour.length, optimum);
+                assert_eq!(instance.tour_length(&tour.cities), optimum);
+                assert_eq!(
+                    tour.cities.iter().sorted().copied().collect::<Vec<_>>(),
+                    (0..n).collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_held_karp_limit() {
+        let points = (0..=HELD_KARP_LIMIT)
+            .map(|i| (i as f64, 0.0))
+            .collect::<Vec<_>>();
+        held_karp(&Instance::from_points("line", &points));
+    }
+
+    #[test]
+    fn test_gr17() {
+        let instance = Instance::from_str(include_str!("../../tsplib/gr17.tsp")).unwrap();
+        assert_eq!(held_karp(&instance).length, 2085);
+        assert_eq!(branch_and_bound(&instance).length, 2085);
+        assert!(one_tree_bound(&instance) <= 2085);
+        // 罰則つきの下界で刈っても最適解を落とさない
+        let (_, penalties) = penalties(&instance, 2085);
+        let worst = Tour::new(&instance, (0..17).collect());
+        assert_eq!(search(&instance, worst, penalties).length, 2085);
+    }
+}
@@ -0,0 +1,192 @@
+//! 順列の遺伝的アルゴリズムに局所探索を組み合わせた解法
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use super::local_search::improve;
+use super::{Instance, Tour};
+use crate::genetic_algorithm::operators::{
+    Crossover, InversionMutation, Mutation, OrderCrossover, Selection, Tournament,
+};
+
+/// 都市の順列を個体とし、子を作るたびに 2-opt と Or-opt で磨く。
+/// 親と子を合わせた中から、長さの異なる短い巡回路を次の世代に残す。
+pub struct GeneticTsp<'a> {
+    instance: &'a Instance,
+    population_size: usize,
+    generations: u32,
+    mutation_chance: f64,
+    crossover_chance: f64,
+    selection: Box<dyn Selection>,
+    crossover: Box<dyn Crossover<Vec<usize>>>,
+    mutation: Box<dyn Mutation<Vec<usize>>>,
+    local_search: bool,
+    rng: ChaCha12Rng,
+}
+
+impl<'a> GeneticTsp<'a> {
+    /// 既定では 3 個体のトーナメントで選び、OX で交叉し、区間の反転で突然変異させる。
+    pub fn new(instance: &'a Instance, population_size: usize, generations: u32) -> Self {
+        Self {
+            instance,
+            population_size: population_size.max(2),
+            generations,
+            mutation_chance: 0.3,
+            crossover_chance: 0.9,
+            selection: Box::new(Tournament::new(3)),
+            crossover: Box::new(OrderCrossover),
+            mutation: Box::new(InversionMutation),
+            local_search: true,
+            rng: ChaCha12Rng::from_entropy(),
+        }
+    }
+
+    /// OX、PMX、CX など順列を保つ交叉
+    pub fn crossover(mut self, crossover: impl Crossover<Vec<usize>> + 'static) -> Self {
+        self.crossover = Box::new(crossover);
+        self
+    }
+
+    /// 順列を保つ突然変異
+    pub fn mutation(mut self, mutation: impl Mutation<Vec<usize>> + 'static) -> Self {
+        self.mutation = Box::new(mutation);
+        self
+    }
+
+    pub fn selection(mut self, selection: impl Selection + 'static) -> Self {
+        self.selection = Box::new(selection);
+        self
+    }
+
+    /// false にすると局所探索をせず、遺伝的アルゴリズムだけで解く。
+    pub fn local_search(mut self, local_search: bool) -> Self {
+        self.local_search = local_search;
+        self
+    }
+
+    /// 同じ seed なら同じ結果になる。
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self
+    }
+
+    fn tour(&self, mut cities: Vec<usize>) -> Tour {
+        if self.local_search {
+            improve(self.instance, &mut cities);
+        }
+        Tour::new(self.instance, cities)
+    }
+
+    /// 最近傍法の巡回路 1 つと無作為な巡回路から始める。
+    fn initial_population(&mut self) -> Vec<Tour> {
+        let n = self.instance.dimension();
+        let mut population = vec![self.tour(self.instance.nearest_neighbour(0))];
+        while population.len() < self.population_size {
+            let mut cities = (0..n).collect::<Vec<_>>();
+            cities.shuffle(&mut self.rng);
+            population.push(self.tour(cities));
+        }
+        population
+    }
+
+    fn offspring(&mut self, population: &[Tour]) -> Vec<Tour> {
+        let fitness = population
+            .iter()
+            .map(|tour| -tour.length as f64)
+            .collect::<Vec<_>>();
+        let size = self.population_size;
+        let parents = self
+            .selection
+            .select(&fitness, size + size % 2, &mut self.rng);
+        let mut offspring = Vec::new();
+        for pair in parents.chunks(2) {
+            let (parent1, parent2) = (&population[pair[0]].cities, &population[pair[1]].cities);
+            let (mut child1, mut child2) = if self.rng.gen_bool(self.crossover_chance) {
+                self.crossover.crossover(parent1, parent2, &mut self.rng)
+            } else {
+                (parent1.clone(), parent2.clone())
+            };
+            for child in [&mut child1, &mut child2] {
+                if self.rng.gen_bool(self.mutation_chance) {
+                    self.mutation.mutate(child, &mut self.rng);
+                }
+            }
+            offspring.push(self.tour(child1));
+            offspring.push(self.tour(child2));
+        }
+        offspring
+    }
+
+    /// いちばん短い巡回路を返す。
+    pub fn run(&mut self) -> Tour {
+        let mut population = self.initial_population();
+        for generation in 0..self.generations {
+            let offspring = self.offspring(&population);
+            population.extend(offspring);
+            population.sort_by_key(|tour| tour.length);
+            // 同じ長さの巡回路ばかりにならないよう、重複は後回しにする
+            let mut survivors: Vec<Tour> = Vec::new();
+            let mut duplicates = Vec::new();
+            for tour in population {
+                if survivors
+                    .last()
+                    .is_some_and(|last| last.length == tour.length)
+                {
+                    duplicates.push(tour);
+                } else {
+                    survivors.push(tour);
+                }
+            }
+            survivors.extend(duplicates);
+            survivors.truncate(self.population_size);
+            population = survivors;
+            tracing::info!(
+                "Generation {} Best {} Avg {}",
+                generation,
+                population[0].length,
+                population
+                    .iter()
+                    .map(|tour| tour.length as f64)
+                    .sum::<f64>()
+                    / population.len() as f64
+            );
+        }
+        population.swap_remove(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::genetic_algorithm::operators::PartiallyMappedCrossover;
+
+    #[test]
+    fn test_gr17() {
+        let instance = Instance::from_str(include_str!("../../tsplib/gr17.tsp")).unwrap();
+        let tour = GeneticTsp::new(&instance, 20, 10).seed(0).run();
+        assert_eq!(tour.length, 2085);
+        assert_eq!(tour.gap(2085), 0.0);
+        let mut cities = tour.cities.clone();
+        cities.sort();
+        assert_eq!(cities, (0..17).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_without_local_search() {
+        let points = (0..10)
+            .map(|i| {
+                let angle = i as f64 / 10.0 * std::f64::consts::TAU;
+                (100.0 * angle.cos(), 100.0 * angle.sin())
+            })
+            .collect::<Vec<_>>();
+        let instance = Instance::from_points("circle", &points);
+        let optimum = instance.tour_length(&(0..10).collect::<Vec<_>>());
+        let tour = GeneticTsp::new(&instance, 50, 100)
+            .crossover(PartiallyMappedCrossover)
+            .local_search(false)
+            .seed(0)
+            .run();
+        assert_eq!(tour.length, optimum);
+    }
+}
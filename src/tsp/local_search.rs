@@ -0,0 +1,120 @@
+//! 巡回路を少しずつ短くする局所探索
+use super::Instance;
+
+/// 2 本の辺を外してつなぎ直す (間の区間を逆にする) と短くなる限り続ける。
+/// 一度でも短くなったら true を返す。
+pub fn two_opt(instance: &Instance, cities: &mut [usize]) -> bool {
+    let n = cities.len();
+    let d = |a: usize, b: usize| instance.distance(a, b);
+    let mut improved = false;
+    loop {
+        let mut changed = false;
+        for i in 0..n.saturating_sub(2) {
+            for j in i + 2..n {
+                let (a, b) = (cities[i], cities[i + 1]);
+                let (c, e) = (cities[j], cities[(j + 1) % n]);
+                if e == a {
+                    continue;
+                }
+                if d(a, c) + d(b, e) < d(a, b) + d(c, e) {
+                    cities[i + 1..=j].reverse();
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return improved;
+        }
+        improved = true;
+    }
+}
+
+/// 1 から 3 都市の区間を、向きを変えることも含めて別の辺の間へ移すと短くなる限り続ける。
+/// 一度でも短くなったら true を返す。
+pub fn or_opt(instance: &Instance, cities: &mut [usize]) -> bool {
+    let n = cities.len();
+    let d = |a: usize, b: usize| instance.distance(a, b);
+    let mut improved = false;
+    'search: loop {
+        for length in 1..=3.min(n.saturating_sub(3)) {
+            for start in 0..=n - length {
+                let (first, last) = (cities[start], cities[start + length - 1]);
+                let previous = cities[(start + n - 1) % n];
+                let next = cities[(start + length) % n];
+                let removed = d(previous, first) + d(last, next) - d(previous, next);
+                for k in 0..n {
+                    // 区間の中や区間に接する辺には入れられない
+                    if k == (start + n - 1) % n || (start..start + length).contains(&k) {
+                        continue;
+                    }
+                    let (p, q) = (cities[k], cities[(k + 1) % n]);
+                    let forward = d(p, first) + d(last, q) - d(p, q);
+                    let backward = d(p, last) + d(first, q) - d(p, q);
+                    if forward.min(backward) < removed {
+                        let mut segment = cities[start..start + length].to_vec();
+                        if backward < forward {
+                            segment.reverse();
+                        }
+                        let mut rest = cities[..start].to_vec();
+                        rest.extend_from_slice(&cities[start + length..]);
+                        let position = rest.iter().position(|c| *c == p).unwrap() + 1;
+                        rest.splice(position..position, segment);
+                        cities.copy_from_slice(&rest);
+                        improved = true;
+                        continue 'search;
+                    }
+                }
+            }
+        }
+        return improved;
+    }
+}
+
+/// 2-opt と Or-opt をどちらも改善できなくなるまで繰り返す。
+pub fn improve(instance: &Instance, cities: &mut [usize]) {
+    loop {
+        two_opt(instance, cities);
+        if !or_opt(instance, cities) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(n: usize) -> Instance {
+        let points = (0..n)
+            .map(|i| {
+                let angle = i as f64 / n as f64 * std::f64::consts::TAU;
+                (1000.0 * angle.cos(), 1000.0 * angle.sin())
+            })
+            .collect::<Vec<_>>();
+        Instance::from_points("circle", &points)
+    }
+
+    #[test]
+    fn test_untangles_circle() {
+        let instance = circle(12);
+        let optimum = instance.tour_length(&(0..12).collect::<Vec<_>>());
+        // 2 か所で交差した巡回路
+        let mut cities = vec![0, 1, 5, 4, 3, 2, 6, 7, 10, 9, 8, 11];
+        assert!(two_opt(&instance, &mut cities));
+        assert_eq!(instance.tour_length(&cities), optimum);
+        assert!(!two_opt(&instance, &mut cities));
+    }
+
+    #[test]
+    fn test_or_opt_moves_city() {
+        let instance = circle(10);
+        let optimum = instance.tour_length(&(0..10).collect::<Vec<_>>());
+        // 都市 2 だけが離れた位置にある
+        let mut cities = vec![0, 1, 3, 4, 5, 6, 2, 7, 8, 9];
+        assert!(or_opt(&instance, &mut cities));
+        assert_eq!(instance.tour_length(&cities), optimum);
+        let mut sorted = cities.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,445 @@
+//! csp で単語を配置するワードサーチ（単語探し）の生成
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::csp::{Constraint, CspError, CSP};
+
+/// 単語を書く向き
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Right,
+    Left,
+    Down,
+    Up,
+    DownRight,
+    DownLeft,
+    UpRight,
+    UpLeft,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::Right,
+        Direction::Left,
+        Direction::Down,
+        Direction::Up,
+        Direction::DownRight,
+        Direction::DownLeft,
+        Direction::UpRight,
+        Direction::UpLeft,
+    ];
+
+    /// (行, 列) の 1 文字ごとの増分
+    fn delta(&self) -> (isize, isize) {
+        match self {
+            Direction::Right => (0, 1),
+            Direction::Left => (0, -1),
+            Direction::Down => (1, 0),
+            Direction::Up => (-1, 0),
+            Direction::DownRight => (1, 1),
+            Direction::DownLeft => (1, -1),
+            Direction::UpRight => (-1, 1),
+            Direction::UpLeft => (-1, -1),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Direction::Right => "right",
+            Direction::Left => "left",
+            Direction::Down => "down",
+            Direction::Up => "up",
+            Direction::DownRight => "down-right",
+            Direction::DownLeft => "down-left",
+            Direction::UpRight => "up-right",
+            Direction::UpLeft => "up-left",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 難しさ。使う向きと、空いたマスを埋める文字が変わる。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Difficulty {
+    /// 右と下だけ
+    Easy,
+    /// 右と下と、右下・右上の斜め
+    #[default]
+    Medium,
+    /// 8 方向すべて。空いたマスも単語に使われている文字で埋める。
+    Hard,
+}
+
+impl Difficulty {
+    pub fn directions(&self) -> Vec<Direction> {
+        match self {
+            Difficulty::Easy => vec![Direction::Right, Direction::Down],
+            Difficulty::Medium => vec![
+                Direction::Right,
+                Direction::Down,
+                Direction::DownRight,
+                Direction::UpRight,
+            ],
+            Difficulty::Hard => Direction::ALL.to_vec(),
+        }
+    }
+}
+
+/// 単語の先頭の位置と向き
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub row: usize,
+    pub column: usize,
+    pub direction: Direction,
+}
+
+impl Placement {
+    /// 長さ len の単語が通るマス
+    pub fn cells(&self, len: usize) -> Vec<(usize, usize)> {
+        let (dr, dc) = self.direction.delta();
+        (0..len as isize)
+            .map(|i| {
+                (
+                    (self.row as isize + dr * i) as usize,
+                    (self.column as isize + dc * i) as usize,
+                )
+            })
+            .collect()
+    }
+}
+
+/// 2 つの単語が重なるマスでは同じ文字でなければならない。
+/// 重なれるのは交差する 1 マスだけで、2 マス以上重なって同じ直線上に並ぶと、
+/// 一方の単語が他方に埋もれてしまう（SALLY の中の SAL など）。
+/// 重なりを許さないときは、同じマスを使うこと自体を禁じる。
+pub struct WordConstraint {
+    words: (String, String),
+    allow_overlap: bool,
+}
+
+impl Constraint<String, Placement> for WordConstraint {
+    fn variables(&self) -> Vec<String> {
+        vec![self.words.0.clone(), self.words.1.clone()]
+    }
+
+    fn satisfied(&self, assignment: &HashMap<String, Placement>) -> bool {
+        let (Some(first), Some(second)) =
+            (assignment.get(&self.words.0), assignment.get(&self.words.1))
+        else {
+            return true;
+        };
+        let letters = first
+            .cells(self.words.0.chars().count())
+            .into_iter()
+            .zip(self.words.0.chars())
+            .collect::<HashMap<_, _>>();
+        let mut shared = 0;
+        second
+            .cells(self.words.1.chars().count())
+            .into_iter()
+            .zip(self.words.1.chars())
+            .all(|(cell, letter)| match letters.get(&cell) {
+                Some(other) => {
+                    shared += 1;
+                    self.allow_overlap && shared == 1 && *other == letter
+                }
+                None => true,
+            })
+    }
+}
+
+/// ワードサーチの設定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordSearch {
+    pub rows: usize,
+    pub columns: usize,
+    pub words: Vec<String>,
+    pub difficulty: Difficulty,
+    /// 同じ文字のマスを複数の単語で共有してよいか
+    pub allow_overlap: bool,
+}
+
+impl WordSearch {
+    pub fn new(rows: usize, columns: usize, words: Vec<String>) -> Self {
+        Self {
+            rows,
+            columns,
+            words,
+            difficulty: Difficulty::default(),
+            allow_overlap: true,
+        }
+    }
+
+    /// 1 行に 1 単語の一覧を読み込む。空白は取り除いて大文字にし、空行と `#` で始まる行は飛ばす。
+    /// 同じ単語が何度あっても 1 つにする。
+    pub fn parse_words(s: &str) -> Vec<String> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                line.chars()
+                    .filter(|c| !c.is_whitespace())
+                    .flat_map(char::to_uppercase)
+                    .collect()
+            })
+            .unique()
+            .collect()
+    }
+
+    /// 長さ len の単語を盤面に収められる位置
+    fn domain(&self, len: usize) -> Vec<Placement> {
+        let mut domain = Vec::new();
+        for direction in self.difficulty.directions() {
+            let (dr, dc) = direction.delta();
+            let span = len as isize - 1;
+            for (row, column) in (0..self.rows).cartesian_product(0..self.columns) {
+                let last_row = row as isize + dr * span;
+                let last_column = column as isize + dc * span;
+                if (0..self.rows as isize).contains(&last_row)
+                    && (0..self.columns as isize).contains(&last_column)
+                {
+                    domain.push(Placement {
+                        row,
+                        column,
+                        direction,
+                    });
+                }
+            }
+        }
+        domain
+    }
+
+    /// 単語の位置を変数とする CSP。shuffle で各ドメインの並びを変えられる。
+    /// 盤面に収まらない単語があれば CspError::EmptyDomain になる。
+    pub fn csp<F>(
+        &self,
+        mut shuffle: F,
+    ) -> Result<CSP<String, Placement, WordConstraint>, CspError<String>>
+    where
+        F: FnMut(&mut Vec<Placement>),
+    {
+        // 長い単語ほど置き場所が少ないので先に置く
+        let variables = self
+            .words
+            .iter()
+            .sorted_by_key(|word| std::cmp::Reverse(word.chars().count()))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut domains = HashMap::new();
+        for word in &variables {
+            let mut domain = self.domain(word.chars().count());
+            shuffle(&mut domain);
+            domains.insert(word.clone(), domain);
+        }
+        let mut csp = CSP::new(variables, domains)?;
+        for (first, second) in self.words.iter().tuple_combinations() {
+            csp.add_constraint(WordConstraint {
+                words: (first.clone(), second.clone()),
+                allow_overlap: self.allow_overlap,
+            })?;
+        }
+        Ok(csp)
+    }
+
+    /// 単語を無作為に配置して、残りのマスを無作為な文字で埋める。配置できなければ None。
+    pub fn generate<R: Rng>(&self, rng: &mut R) -> Result<Option<Puzzle>, CspError<String>> {
+        let csp = self.csp(|domain| domain.shuffle(rng))?;
+        let Some(solution) = csp.backtracking_search() else {
+            return Ok(None);
+        };
+        let placements = self
+            .words
+            .iter()
+            .map(|word| (word.clone(), solution[word]))
+            .collect::<Vec<_>>();
+
+        let mut answer = vec![vec![None; self.columns]; self.rows];
+        for (word, placement) in &placements {
+            for ((row, column), letter) in placement
+                .cells(word.chars().count())
+                .into_iter()
+                .zip(word.chars())
+            {
+                answer[row][column] = Some(letter);
+            }
+        }
+        let fillers = match self.difficulty {
+            Difficulty::Hard => self
+                .words
+                .iter()
+                .flat_map(|word| word.chars())
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .sorted()
+                .collect(),
+            _ => ('A'..='Z').collect::<Vec<_>>(),
+        };
+        let grid = answer
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|letter| letter.unwrap_or_else(|| *fillers.choose(rng).unwrap()))
+                    .collect()
+            })
+            .collect();
+        Ok(Some(Puzzle {
+            grid,
+            answer,
+            placements,
+        }))
+    }
+}
+
+/// 生成したワードサーチ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    grid: Vec<Vec<char>>,
+    answer: Vec<Vec<Option<char>>>,
+    placements: Vec<(String, Placement)>,
+}
+
+impl Puzzle {
+    pub fn grid(&self) -> &[Vec<char>] {
+        &self.grid
+    }
+
+    /// 単語と、その先頭の位置と向き
+    pub fn placements(&self) -> &[(String, Placement)] {
+        &self.placements
+    }
+
+    /// 単語のマスだけを残した盤面と、単語ごとの位置と向き
+    pub fn answer_key(&self) -> String {
+        let mut key = String::new();
+        for row in &self.answer {
+            key.extend(row.iter().map(|letter| letter.unwrap_or('.')));
+            key.push('\n');
+        }
+        for (word, placement) in &self.placements {
+            key.push_str(&format!(
+                "{} ({}, {}) {}\n",
+                word, placement.row, placement.column, placement.direction
+            ));
+        }
+        key
+    }
+}
+
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.grid {
+            writeln!(f, "{}", row.iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn test_domain() {
+        let mut word_search = WordSearch::new(3, 4, Vec::new());
+        word_search.difficulty = Difficulty::Easy;
+        // 横は 1 行に 2 か所 × 3 行、縦は 1 列に 1 か所 × 4 列
+        assert_eq!(word_search.domain(3).len(), 10);
+        word_search.difficulty = Difficulty::Hard;
+        assert!(word_search
+            .domain(3)
+            .iter()
+            .all(|p| p.cells(3).iter().all(|(r, c)| *r < 3 && *c < 4)));
+        assert_eq!(word_search.domain(5), Vec::new());
+    }
+
+    #[test]
+    fn test_overlap() {
+        let constraint = |allow_overlap| WordConstraint {
+            words: ("CAT".to_string(), "TOP".to_string()),
+            allow_overlap,
+        };
+        let mut assignment = HashMap::new();
+        let at = |row, column, direction| Placement {
+            row,
+            column,
+            direction,
+        };
+        assignment.insert("CAT".to_string(), at(0, 0, Direction::Right));
+        // T を共有する
+        assignment.insert("TOP".to_string(), at(0, 2, Direction::Down));
+        assert!(constraint(true).satisfied(&assignment));
+        assert!(!constraint(false).satisfied(&assignment));
+        // A と T が重なる
+        assignment.insert("TOP".to_string(), at(0, 1, Direction::Down));
+        assert!(!constraint(true).satisfied(&assignment));
+    }
+
+    #[test]
+    fn test_word_inside_word() {
+        let constraint = |first: &str, second: &str| WordConstraint {
+            words: (first.to_string(), second.to_string()),
+            allow_overlap: true,
+        };
+        let at = |row, column, direction| Placement {
+            row,
+            column,
+            direction,
+        };
+        let mut assignment = HashMap::new();
+        assignment.insert("SALLY".to_string(), at(0, 0, Direction::Right));
+        // SAL が SALLY の中に埋もれる
+        assignment.insert("SAL".to_string(), at(0, 0, Direction::Right));
+        assert!(!constraint("SALLY", "SAL").satisfied(&assignment));
+        assert!(!constraint("SAL", "SALLY").satisfied(&assignment));
+        // 逆向きに重なる
+        assignment.insert("LAS".to_string(), at(0, 2, Direction::Left));
+        assert!(!constraint("SALLY", "LAS").satisfied(&assignment));
+        // S だけを共有して交差するならよい
+        assignment.insert("SAL".to_string(), at(0, 0, Direction::Down));
+        assert!(constraint("SALLY", "SAL").satisfied(&assignment));
+    }
+
+    #[test]
+    fn test_generate() {
+        let mut rng = StdRng::seed_from_u64(34);
+        let mut word_search = WordSearch::new(6, 7, words(&["MATTHEW", "JOÉ", "MARY", "SARAH"]));
+        word_search.difficulty = Difficulty::Hard;
+        let puzzle = word_search.generate(&mut rng).unwrap().unwrap();
+        assert_eq!(puzzle.grid().len(), 6);
+        for (word, placement) in puzzle.placements() {
+            let letters = placement
+                .cells(word.chars().count())
+                .into_iter()
+                .map(|(row, column)| puzzle.grid()[row][column])
+                .collect::<String>();
+            assert_eq!(&letters, word);
+        }
+    }
+
+    #[test]
+    fn test_word_too_long() {
+        let word_search = WordSearch::new(3, 3, words(&["JOE", "MARY"]));
+        assert_eq!(
+            word_search.generate(&mut StdRng::seed_from_u64(34)).err(),
+            Some(CspError::EmptyDomain("MARY".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_words() {
+        assert_eq!(
+            WordSearch::parse_words("# names\nmatthew\n\n new york \nMatthew\njoé"),
+            words(&["MATTHEW", "NEWYORK", "JOÉ"])
+        );
+    }
+}